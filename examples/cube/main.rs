@@ -2,7 +2,7 @@ use flexi_logger::{colored_default_format, Logger};
 use mutunga::{Cell, Color, Event, TerminalCanvas};
 use std::error::Error;
 use std::{thread, time};
-use toru::Canvas;
+use toru::{Canvas, Scene};
 
 mod scene;
 mod shaders;
@@ -35,6 +35,7 @@ fn main() -> Result<(), Box<dyn Error>> {
 	term.attach()?;
 
 	// Main application loop
+	let mut last_tick_at = time::Instant::now();
 	loop {
 		let current_start = time::Instant::now();
 
@@ -50,7 +51,9 @@ fn main() -> Result<(), Box<dyn Error>> {
 			}
 		}
 
-		// Render the 3D scene to the canvas
+		// Animate and render the 3D scene to the canvas
+		scene.update(last_tick_at.elapsed().as_secs_f32());
+		last_tick_at = time::Instant::now();
 		scene.draw(&mut canvas);
 
 		// Draw each pixel to the terminal
@@ -2,11 +2,12 @@ use super::shaders::*;
 use mutunga::Color;
 use nalgebra as na;
 use std::f32::consts::PI;
-use std::time;
-use toru::{Canvas, Cube, FreeCamera, Mesh, Texture};
+use toru::{Canvas, Cube, FreeCamera, Mesh, MipmapFilter, Scene, Texture, TextureFilter};
+
+// Half the width of the cube
+const CUBE_SIZE: f32 = 0.3;
 
 pub struct CubeScene {
-	last_tick_at: time::Instant,
 	program: CubeProgram,
 	camera: FreeCamera,
 	vertices: Vec<CubeVertex>,
@@ -16,13 +17,16 @@ pub struct CubeScene {
 impl CubeScene {
 	pub fn new() -> Self {
 		// Load texture image
-		let texture: Texture<Color> = Texture::load("examples/assets/checker.png").expect("Couldn't find the texture");
+		let mut texture: Texture<Color> = Texture::load("examples/assets/checker.png")
+			.expect("Couldn't find the texture")
+			.with_mipmaps(MipmapFilter::GammaCorrect);
+		*texture.filter_mut() = TextureFilter::Trilinear;
 
 		// Setup some shaders
 		let vertex_shader = CubeVertexShader::new();
-		let fragment_shader = CubeFragmentShader::new(texture);
+		let fragment_shader = CubeFragmentShader::new(texture, CUBE_SIZE * 2.0);
 
-		let cube = Cube::new(0.3, Color::rgb(255, 0, 0).into());
+		let cube = Cube::new(CUBE_SIZE, Color::rgb(255, 0, 0).into());
 		let mut vertices = Vec::with_capacity(36);
 		for tri in cube.triangles() {
 			for i in 0..3 {
@@ -39,23 +43,20 @@ impl CubeScene {
 		}
 
 		CubeScene {
-			last_tick_at: time::Instant::now(),
 			program: CubeProgram::new(vertex_shader, fragment_shader),
 			vertices,
 			camera: FreeCamera::new(1.0, 1.0),
 			transform: na::Matrix4::identity(),
 		}
 	}
-	pub fn update(&mut self) {
-		let dt = self.last_tick_at.elapsed().as_secs_f32();
-		self.last_tick_at = time::Instant::now();
-
-		self.program.vertex_shader.set_camera(&self.camera);
+}
 
+impl Scene<Color> for CubeScene {
+	fn update(&mut self, dt: f32) {
 		self.transform *= na::Matrix4::from_euler_angles(0.321 * PI * dt, 0.0, -0.234 * PI * dt);
 	}
 
-	pub fn draw(&mut self, canvas: &mut Canvas<Color>) {
+	fn draw(&mut self, canvas: &mut Canvas<Color>) {
 		let mut ctx = canvas.context();
 
 		let w = ctx.width() as f32;
@@ -64,7 +65,8 @@ impl CubeScene {
 		if w != self.camera.width() || h != self.camera.height() {
 			self.camera.resize(w, h);
 		}
-		self.update();
+		self.program.vertex_shader.set_camera(&self.camera);
+		self.program.fragment_shader.set_camera(&self.camera);
 
 		ctx.clear();
 		self.program.vertex_shader.set_model(&self.transform);
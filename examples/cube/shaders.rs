@@ -1,6 +1,6 @@
 use mutunga::Color;
 use nalgebra as na;
-use toru::{Camera, FragmentShader, Program, Texture, Varyings, Vertex, VertexShader};
+use toru::{Camera, FragmentShader, FreeCamera, Program, Texture, Varyings, Vertex, VertexShader};
 
 pub type CubeProgram = Program<CubeVertexShader, CubeFragmentShader, CubeVertex, CubeVaryings, Color>;
 
@@ -29,6 +29,10 @@ pub struct CubeVertexShader {
 
 pub struct CubeFragmentShader {
 	pub texture: Texture<Color>,
+	// Texels per world unit across a face of the cube
+	texel_density: f32,
+	// Height of a pixel in world units at a distance of 1.0 from the camera
+	pixel_size: f32,
 }
 
 impl Vertex for CubeVertex {}
@@ -82,8 +86,18 @@ impl VertexShader<CubeVertex, CubeVaryings> for CubeVertexShader {
 }
 
 impl CubeFragmentShader {
-	pub fn new(texture: Texture<Color>) -> Self {
-		CubeFragmentShader { texture }
+	/// `face_size` is the width of a cube face in world units, which the texture is stretched across
+	pub fn new(texture: Texture<Color>, face_size: f32) -> Self {
+		let texel_density = texture.width() as f32 / face_size;
+		CubeFragmentShader {
+			texture,
+			texel_density,
+			pixel_size: 0.0,
+		}
+	}
+
+	pub fn set_camera(&mut self, camera: &FreeCamera) {
+		self.pixel_size = 2.0 / (camera.projection()[(1, 1)] * camera.height());
 	}
 }
 
@@ -136,7 +150,12 @@ impl FragmentShader<CubeVaryings, Color> for CubeFragmentShader {
 		// Apply perspective correction to texture
 		let u = varyings.uv.x / varyings.uv.z;
 		let v = varyings.uv.y / varyings.uv.z;
-		let mut color = self.texture.get_normalized_pixel(u, v);
+
+		// Pick the mip level from how many texels a pixel covers at this depth. The UV's `z` is 1 / w,
+		// and treating the footprint as square is close enough for a small spinning cube.
+		let depth = 1.0 / varyings.uv.z;
+		let lod = (self.texel_density * self.pixel_size * depth).log2();
+		let mut color = self.texture.sample_lod(u, v, lod);
 
 		color.set_brightness(varyings.brightness);
 
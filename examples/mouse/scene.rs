@@ -1,7 +1,7 @@
 use std::f32::consts::PI;
 use super::shaders::*;
 use mutunga::Color;
-use toru::{Canvas, Mesh, OrbitCamera, StaticMesh};
+use toru::{Canvas, Mesh, OrbitCamera, Scene, StaticMesh};
 
 pub struct MouseScene {
 	pub camera: OrbitCamera,
@@ -41,11 +41,13 @@ impl MouseScene {
 		}
 	}
 
-	pub fn update(&mut self) {
-		self.program.vertex_shader.set_camera(&self.camera);
-	}
+}
 
-	pub fn draw(&mut self, canvas: &mut Canvas<Color>) {
+impl Scene<Color> for MouseScene {
+	// Only moves when the camera is dragged
+	fn update(&mut self, _dt: f32) {}
+
+	fn draw(&mut self, canvas: &mut Canvas<Color>) {
 		let mut ctx = canvas.context();
 
 		let w = ctx.width() as f32;
@@ -54,7 +56,7 @@ impl MouseScene {
 		if w != self.camera.width() || h != self.camera.height() {
 			self.camera.resize(w, h);
 		}
-		self.update();
+		self.program.vertex_shader.set_camera(&self.camera);
 
 		ctx.clear();
 		ctx.draw_triangles(&mut self.program, self.vertices.iter());
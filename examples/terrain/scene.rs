@@ -3,8 +3,7 @@ use mutunga::Color;
 use nalgebra as na;
 use noise::{NoiseFn, OpenSimplex};
 use std::f32::consts::PI;
-use std::time;
-use toru::{Canvas, FreeCamera, Texture};
+use toru::{Canvas, FreeCamera, Scene, Texture};
 
 const MESH_RES: isize = 16;
 const TEXTURE_RES: u32 = 128;
@@ -38,7 +37,6 @@ fn create_quad(x: isize, y: isize) -> Vec<TerrainVertex> {
 }
 
 pub struct TerrainScene {
-	last_tick_at: time::Instant,
 	program: TerrainProgram,
 	camera: FreeCamera,
 	vertices: Vec<TerrainVertex>,
@@ -77,23 +75,20 @@ impl TerrainScene {
 			* na::Matrix4::new_scaling(2.0);
 
 		TerrainScene {
-			last_tick_at: time::Instant::now(),
 			program: TerrainProgram::new(vertex_shader, fragment_shader),
 			vertices,
 			camera: FreeCamera::new(1.0, 1.0),
 			transform,
 		}
 	}
-	pub fn update(&mut self) {
-		let dt = self.last_tick_at.elapsed().as_secs_f32();
-		self.last_tick_at = time::Instant::now();
-
-		self.program.vertex_shader.set_camera(&self.camera);
+}
 
+impl Scene<Color> for TerrainScene {
+	fn update(&mut self, dt: f32) {
 		self.transform *= na::Matrix4::from_euler_angles(0.0, 0.0, -0.234 * PI * dt);
 	}
 
-	pub fn draw(&mut self, canvas: &mut Canvas<Color>) {
+	fn draw(&mut self, canvas: &mut Canvas<Color>) {
 		let mut ctx = canvas.context();
 
 		let w = ctx.width() as f32;
@@ -102,7 +97,7 @@ impl TerrainScene {
 		if w != self.camera.width() || h != self.camera.height() {
 			self.camera.resize(w, h);
 		}
-		self.update();
+		self.program.vertex_shader.set_camera(&self.camera);
 
 		ctx.clear();
 		self.program.vertex_shader.set_model(&self.transform);
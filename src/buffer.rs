@@ -1,3 +1,18 @@
+mod blit;
+pub use blit::*;
+mod fill;
+pub use fill::*;
+mod filter;
+pub use filter::*;
+mod flood;
+pub use flood::*;
+mod resample;
+pub use resample::*;
+mod shapes;
+pub use shapes::*;
+mod view;
+pub use view::*;
+
 use crate::geom::Rect;
 use std::mem::size_of;
 
@@ -12,6 +27,18 @@ pub trait Blendable: Default + Copy + PartialEq {
 
 	fn set_brightness(&mut self, _brightness: f32) {}
 	fn lerp(&self, other: &Self, amount: f32) -> Self;
+
+	/// Red, green and blue in the range `0.0..=1.0`, or above for HDR colours, e.g. for choosing the
+	/// colours of a terminal cell. Pixel types without colours are black unless they override this.
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[0.0; 3]
+	}
+
+	/// Perceived brightness of the pixel in the range `0.0..=1.0`, or above for HDR colours
+	fn luminance(&self) -> f32 {
+		let [r, g, b] = self.to_rgb_floats();
+		0.2126 * r + 0.7152 * g + 0.0722 * b
+	}
 }
 
 impl Blendable for f32 {
@@ -42,6 +69,14 @@ impl Blendable for f32 {
 	fn lerp(&self, other: &Self, a: f32) -> f32 {
 		self * (1.0 - a) + other * a
 	}
+
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[*self; 3]
+	}
+
+	fn luminance(&self) -> f32 {
+		*self
+	}
 }
 
 impl Blendable for u8 {
@@ -72,6 +107,51 @@ impl Blendable for u8 {
 	fn lerp(&self, other: &Self, a: f32) -> u8 {
 		((*self as f32) * (1.0 - a) + (*other as f32) * a) as u8
 	}
+
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[*self as f32 / 255.0; 3]
+	}
+
+	fn luminance(&self) -> f32 {
+		*self as f32 / 255.0
+	}
+}
+
+/// For object ID buffers. Blending replaces and interpolation picks the nearest, so IDs are never mixed.
+impl Blendable for u32 {
+	// The ID being drawn replaces the one underneath
+	fn blend(&self, _other: &u32) -> u32 {
+		*self
+	}
+
+	// IDs have no colour, so red, green and blue are just placeholder IDs 1, 2 and 3
+	fn red() -> Self {
+		1
+	}
+
+	fn green() -> Self {
+		2
+	}
+
+	fn blue() -> Self {
+		3
+	}
+
+	fn lerp(&self, other: &Self, a: f32) -> u32 {
+		if a < 0.5 {
+			*self
+		} else {
+			*other
+		}
+	}
+
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[*self as f32 / u32::MAX as f32; 3]
+	}
+
+	fn luminance(&self) -> f32 {
+		*self as f32 / u32::MAX as f32
+	}
 }
 
 #[cfg(feature = "mutunga")]
@@ -81,6 +161,10 @@ impl Blendable for mutunga::Color {
 		let (bg_r, bg_g, bg_b, bg_a) = bg.as_floats();
 
 		let a = (1.0 - fg_a) * bg_a + fg_a;
+		// Both fully transparent, avoid dividing by zero
+		if a <= 0.0 {
+			return mutunga::Color::transparent();
+		}
 		let r = ((1.0 - fg_a) * bg_a * bg_r + fg_a * fg_r) / a;
 		let g = ((1.0 - fg_a) * bg_a * bg_g + fg_a * fg_g) / a;
 		let b = ((1.0 - fg_a) * bg_a * bg_b + fg_a * fg_b) / a;
@@ -124,6 +208,10 @@ impl Blendable for mutunga::Color {
 			mutunga::Color::rgba(r, g, b, a)
 		}
 	}
+
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0]
+	}
 }
 
 #[derive(Default, Clone, Debug)]
@@ -136,28 +224,53 @@ pub struct Buffer<T: Blendable> {
 pub struct BufferRegion<'a, T: Blendable> {
 	pub(crate) buffer: &'a mut Buffer<T>,
 	pub(crate) rect: Rect,
+	// Pixels that can be written to, in buffer coordinates. Always inside `rect` and the buffer.
+	pub(crate) clip: Rect,
 }
 
 impl<'a, T: Blendable> BufferRegion<'a, T> {
+	/// Region inside this one, offset by this region's position and clipped to its bounds
 	pub fn region_mut(&mut self, rect: Rect) -> BufferRegion<T> {
+		let rect = Rect::new(rect.x + self.rect.x, rect.y + self.rect.y, rect.width, rect.height);
+		let clip = rect.intersection(&self.clip).unwrap_or_default();
 		BufferRegion {
 			buffer: self.buffer,
-			rect: Rect::new(rect.x + self.rect.x, rect.y + self.rect.y, rect.width, rect.height),
+			rect,
+			clip,
 		}
 	}
 
 	pub fn width(&self) -> u32 {
-		self.rect.width as u32
+		self.rect.width.max(0) as u32
 	}
 
 	pub fn height(&self) -> u32 {
-		self.rect.height as u32
+		self.rect.height.max(0) as u32
 	}
 
 	pub fn size(&self) -> (u32, u32) {
 		(self.width(), self.height())
 	}
 
+	/// Pixel at `x`, `y` relative to the region, `None` if it's outside the region
+	pub fn get(&self, x: i32, y: i32) -> Option<&T> {
+		let (x, y) = (x + self.rect.x, y + self.rect.y);
+		if self.clip.contains(x, y) {
+			self.buffer.get(x, y)
+		} else {
+			None
+		}
+	}
+
+	pub fn get_mut(&mut self, x: i32, y: i32) -> Option<&mut T> {
+		let (x, y) = (x + self.rect.x, y + self.rect.y);
+		if self.clip.contains(x, y) {
+			self.buffer.get_mut(x, y)
+		} else {
+			None
+		}
+	}
+
 	pub fn clear(&mut self) {
 		self.fill(T::default());
 	}
@@ -167,15 +280,26 @@ impl<'a, T: Blendable> BufferRegion<'a, T> {
 	}
 
 	pub fn fill_rect(&mut self, pixel: T, rect: &Rect) {
-		let mut rect = rect.clone();
-		rect.x += self.rect.x;
-		rect.y += self.rect.y;
-		self.buffer.fill_rect(pixel, &rect);
+		let rect = Rect::new(rect.x + self.rect.x, rect.y + self.rect.y, rect.width, rect.height);
+		if let Some(rect) = rect.intersection(&self.clip) {
+			self.buffer.fill_rect(pixel, &rect);
+		}
 	}
 
+	/// Draw all of `buffer` with its top left corner at `x`, `y`, blending it over the region
 	pub fn draw_buffer(&mut self, x: i32, y: i32, buffer: &Buffer<T>) {
-		// FIXME clip inside region
-		self.buffer.draw_buffer(x + self.rect.x, y + self.rect.y, buffer);
+		let dst = Rect::new(x, y, buffer.width() as i32, buffer.height() as i32);
+		self.blit(buffer, &Blit::new().with_dst(dst));
+	}
+
+	// Writable area relative to the region
+	fn local_clip(&self) -> Rect {
+		Rect::new(
+			self.clip.x - self.rect.x,
+			self.clip.y - self.rect.y,
+			self.clip.width,
+			self.clip.height,
+		)
 	}
 }
 
@@ -195,6 +319,90 @@ impl<T: Blendable> Buffer<T> {
 		self.data.as_slice()
 	}
 
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		self.data.as_mut_slice()
+	}
+
+	/// Pixels of row `y`, left to right
+	pub fn row(&self, y: u32) -> Option<&[T]> {
+		if y >= self.height {
+			return None;
+		}
+		let w = self.width as usize;
+		self.data.get(y as usize * w..(y as usize + 1) * w)
+	}
+
+	pub fn row_mut(&mut self, y: u32) -> Option<&mut [T]> {
+		if y >= self.height {
+			return None;
+		}
+		let w = self.width as usize;
+		self.data.get_mut(y as usize * w..(y as usize + 1) * w)
+	}
+
+	/// Each row from top to bottom
+	pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+		// Empty when the width is 0, but chunks must not be
+		self.data.chunks_exact(self.width.max(1) as usize)
+	}
+
+	pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [T]> {
+		self.data.chunks_exact_mut(self.width.max(1) as usize)
+	}
+
+	/// Pixels of column `x`, top to bottom
+	pub fn column(&self, x: u32) -> Option<impl Iterator<Item = &T>> {
+		if x >= self.width {
+			return None;
+		}
+		Some(self.data.iter().skip(x as usize).step_by(self.width as usize))
+	}
+
+	/// Each column from left to right
+	pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &T>> {
+		(0..self.width).filter_map(move |x| self.column(x))
+	}
+
+	/// Every pixel along with its `x` and `y` position, row by row
+	pub fn enumerate_pixels(&self) -> impl Iterator<Item = (u32, u32, &T)> {
+		let w = self.width.max(1);
+		self.data
+			.iter()
+			.enumerate()
+			.map(move |(i, p)| (i as u32 % w, i as u32 / w, p))
+	}
+
+	pub fn enumerate_pixels_mut(&mut self) -> impl Iterator<Item = (u32, u32, &mut T)> {
+		let w = self.width.max(1);
+		self.data
+			.iter_mut()
+			.enumerate()
+			.map(move |(i, p)| (i as u32 % w, i as u32 / w, p))
+	}
+
+	/// New buffer of the same size, with every pixel converted by `f`
+	pub fn map<U: Blendable>(&self, f: impl FnMut(&T) -> U) -> Buffer<U> {
+		Buffer {
+			width: self.width,
+			height: self.height,
+			data: self.data.iter().map(f).collect(),
+		}
+	}
+
+	/// Pixels at the same position in both buffers, e.g. a colour and its depth.
+	///
+	/// Panics if the buffers are different sizes.
+	pub fn zip<'a, U: Blendable>(&'a self, other: &'a Buffer<U>) -> impl Iterator<Item = (&'a T, &'a U)> {
+		assert_eq!(self.size(), other.size(), "zipped buffers must be the same size");
+		self.data.iter().zip(other.data.iter())
+	}
+
+	/// Like `zip`, but the pixels of this buffer can be changed
+	pub fn zip_mut<'a, U: Blendable>(&'a mut self, other: &'a Buffer<U>) -> impl Iterator<Item = (&'a mut T, &'a U)> {
+		assert_eq!(self.size(), other.size(), "zipped buffers must be the same size");
+		self.data.iter_mut().zip(other.data.iter())
+	}
+
 	pub fn as_bytes(&self) -> &[u8] {
 		let bytes_per_item = size_of::<T>();
 		let byte_size = self.data.len() * bytes_per_item;
@@ -205,11 +413,20 @@ impl<T: Blendable> Buffer<T> {
 	}
 
 	pub fn region_mut(&mut self, rect: Rect) -> BufferRegion<T> {
-		BufferRegion { buffer: self, rect }
+		let clip = rect.intersection(&self.bounds()).unwrap_or_default();
+		BufferRegion {
+			buffer: self,
+			rect,
+			clip,
+		}
 	}
 
 	pub fn as_region_mut(&mut self) -> BufferRegion<T> {
-		self.region_mut(Rect::new(0, 0, self.width as i32, self.height as i32))
+		self.region_mut(self.bounds())
+	}
+
+	pub fn bounds(&self) -> Rect {
+		Rect::new(0, 0, self.width as i32, self.height as i32)
 	}
 
 	pub fn clear(&mut self) {
@@ -221,6 +438,10 @@ impl<T: Blendable> Buffer<T> {
 	}
 
 	pub fn fill_rect(&mut self, new_pixel: T, rect: &Rect) {
+		let rect = match rect.intersection(&self.bounds()) {
+			Some(rect) => rect,
+			None => return,
+		};
 		for y in 0..rect.height {
 			for x in 0..rect.width {
 				let px = x + rect.x;
@@ -327,22 +548,6 @@ impl<T: Blendable> Buffer<T> {
 	}
 
 	pub fn draw_buffer(&mut self, dx: i32, dy: i32, buffer: &Buffer<T>) {
-		let mut width = buffer.width() as i32;
-		let mut height = buffer.height() as i32;
-		if width + dx > self.width() as i32 {
-			width = self.width() as i32 - dx;
-		}
-		if height + dy > self.height() as i32 {
-			height = self.height() as i32 - dy;
-		}
-		for y in 0..height {
-			for x in 0..width {
-				if let Some(dst_pixel) = self.get_mut(x + dx, y + dy) {
-					if let Some(src_pixel) = buffer.get(x, y) {
-						*dst_pixel = src_pixel.blend(dst_pixel);
-					}
-				}
-			}
-		}
+		self.as_region_mut().draw_buffer(dx, dy, buffer);
 	}
 }
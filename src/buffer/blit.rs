@@ -0,0 +1,290 @@
+use super::{Blendable, Buffer, BufferRegion};
+use crate::geom::Rect;
+use crate::texture::TextureFilter;
+
+/// How copied pixels are combined with the pixels underneath them
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendMode {
+	/// Draw over the destination with the pixel type's `Blendable::blend`
+	#[default]
+	Blend,
+	/// Overwrite the destination, including its alpha
+	Replace,
+}
+
+/// Options for copying part of one buffer onto another, e.g. drawing sprites or compositing canvases
+#[derive(Clone, Debug, PartialEq)]
+pub struct Blit<T: Blendable> {
+	/// Area of the source to copy, all of it when `None`
+	pub src: Option<Rect>,
+	/// Area to draw into, relative to the destination region. The source is scaled to fit. When
+	/// `None` the source is drawn unscaled at the top left corner.
+	pub dst: Option<Rect>,
+	/// Used when scaling
+	pub filter: TextureFilter,
+	pub flip_x: bool,
+	pub flip_y: bool,
+	/// Source pixels equal to this are skipped, for sprites without an alpha channel
+	pub color_key: Option<T>,
+	pub blend_mode: BlendMode,
+}
+
+impl<T: Blendable> Default for Blit<T> {
+	fn default() -> Self {
+		Self {
+			src: None,
+			dst: None,
+			filter: TextureFilter::Nearest,
+			flip_x: false,
+			flip_y: false,
+			color_key: None,
+			blend_mode: BlendMode::Blend,
+		}
+	}
+}
+
+impl<T: Blendable> Blit<T> {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn with_src(mut self, src: Rect) -> Self {
+		self.src = Some(src);
+		self
+	}
+
+	pub fn with_dst(mut self, dst: Rect) -> Self {
+		self.dst = Some(dst);
+		self
+	}
+
+	pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+		self.filter = filter;
+		self
+	}
+
+	/// Mirror the source horizontally and/or vertically
+	pub fn flipped(mut self, flip_x: bool, flip_y: bool) -> Self {
+		self.flip_x = flip_x;
+		self.flip_y = flip_y;
+		self
+	}
+
+	pub fn with_color_key(mut self, color_key: T) -> Self {
+		self.color_key = Some(color_key);
+		self
+	}
+
+	pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+		self.blend_mode = blend_mode;
+		self
+	}
+}
+
+impl<'a, T: Blendable> BufferRegion<'a, T> {
+	/// Copy pixels from `src` into the region, clipped to the region's bounds
+	pub fn blit(&mut self, src: &Buffer<T>, blit: &Blit<T>) {
+		let src_rect = blit.src.clone().unwrap_or_else(|| src.bounds());
+		// Parts of the source rect outside of the source buffer are left undrawn
+		let area = match src_rect.intersection(&src.bounds()) {
+			Some(area) => area,
+			None => return,
+		};
+		let dst_rect = blit
+			.dst
+			.clone()
+			.unwrap_or_else(|| Rect::new(0, 0, src_rect.width, src_rect.height));
+		let visible = match dst_rect.intersection(&self.local_clip()) {
+			Some(visible) => visible,
+			None => return,
+		};
+
+		let scale_x = src_rect.width as f32 / dst_rect.width as f32;
+		let scale_y = src_rect.height as f32 / dst_rect.height as f32;
+		for y in visible.y..visible.y + visible.height {
+			// Source position of the destination pixel's center
+			let mut v = (y - dst_rect.y) as f32 + 0.5;
+			if blit.flip_y {
+				v = dst_rect.height as f32 - v;
+			}
+			let v = src_rect.y as f32 + v * scale_y;
+			for x in visible.x..visible.x + visible.width {
+				let mut u = (x - dst_rect.x) as f32 + 0.5;
+				if blit.flip_x {
+					u = dst_rect.width as f32 - u;
+				}
+				let u = src_rect.x as f32 + u * scale_x;
+
+				let pixel = match sample(src, &area, u, v, blit.filter, blit.color_key.as_ref()) {
+					Some(pixel) => pixel,
+					None => continue,
+				};
+				if let Some(dst) = self.get_mut(x, y) {
+					*dst = match blit.blend_mode {
+						BlendMode::Blend => pixel.blend(dst),
+						BlendMode::Replace => pixel,
+					};
+				}
+			}
+		}
+	}
+}
+
+// Pixel at `u`, `v` in source pixel coordinates, or `None` if the nearest pixel is outside `area` or
+// matches the color key
+fn sample<T: Blendable>(
+	src: &Buffer<T>,
+	area: &Rect,
+	u: f32,
+	v: f32,
+	filter: TextureFilter,
+	color_key: Option<&T>,
+) -> Option<T> {
+	let (x, y) = (u.floor() as i32, v.floor() as i32);
+	if !area.contains(x, y) {
+		return None;
+	}
+	let nearest = *src.get(x, y)?;
+	if color_key == Some(&nearest) {
+		return None;
+	}
+
+	match filter {
+		// No mip levels to choose from when blitting
+		TextureFilter::Nearest | TextureFilter::NearestMipmap => Some(nearest),
+		TextureFilter::Bilinear | TextureFilter::Trilinear => {
+			let (fx, fy) = (u - 0.5, v - 0.5);
+			let (x0, y0) = (fx.floor() as i32, fy.floor() as i32);
+			let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+			let right = area.x + area.width - 1;
+			let bottom = area.y + area.height - 1;
+			let texel = |x: i32, y: i32| {
+				let pixel = src.get(x.clamp(area.x, right), y.clamp(area.y, bottom)).copied();
+				// Keyed pixels would bleed into the edges of the sprite
+				match pixel {
+					Some(pixel) if color_key != Some(&pixel) => pixel,
+					_ => nearest,
+				}
+			};
+			let upper = texel(x0, y0).lerp(&texel(x0 + 1, y0), tx);
+			let lower = texel(x0, y0 + 1).lerp(&texel(x0 + 1, y0 + 1), tx);
+			Some(upper.lerp(&lower, ty))
+		}
+	}
+}
+
+impl<T: Blendable> Buffer<T> {
+	pub fn blit(&mut self, src: &Buffer<T>, blit: &Blit<T>) {
+		self.as_region_mut().blit(src, blit);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// 3x2 sprite where each pixel is tagged with its position
+	fn sprite() -> Buffer<u32> {
+		let mut sprite = Buffer::new(3, 2);
+		for (i, tag) in [1, 2, 3, 11, 12, 13].iter().enumerate() {
+			*sprite.get_mut(i as i32 % 3, i as i32 / 3).unwrap() = *tag;
+		}
+		sprite
+	}
+
+	fn rows<T: Blendable>(buffer: &Buffer<T>) -> Vec<Vec<T>> {
+		(0..buffer.height() as i32)
+			.map(|y| (0..buffer.width() as i32).map(|x| *buffer.get(x, y).unwrap()).collect())
+			.collect()
+	}
+
+	// Blit the sprite onto an empty 4x4 buffer
+	fn blit_sprite(blit: Blit<u32>) -> Vec<Vec<u32>> {
+		let mut dst = Buffer::new(4, 4);
+		dst.blit(&sprite(), &blit);
+		rows(&dst)
+	}
+
+	#[test]
+	fn clipped_at_each_edge() {
+		let blit = |x, y| blit_sprite(Blit::new().with_dst(Rect::new(x, y, 3, 2)));
+		assert_eq!(blit(0, 0), [[1, 2, 3, 0], [11, 12, 13, 0], [0; 4], [0; 4]]);
+		assert_eq!(blit(-1, -1), [[12, 13, 0, 0], [0; 4], [0; 4], [0; 4]]);
+		assert_eq!(blit(2, 3), [[0; 4], [0; 4], [0; 4], [0, 0, 1, 2]]);
+		assert_eq!(blit(-2, 2), [[0; 4], [0; 4], [3, 0, 0, 0], [13, 0, 0, 0]]);
+		assert_eq!(blit(3, -1), [[0, 0, 0, 11], [0; 4], [0; 4], [0; 4]]);
+		assert_eq!(blit(4, 0), [[0; 4]; 4]);
+		assert_eq!(blit(0, -2), [[0; 4]; 4]);
+	}
+
+	#[test]
+	fn flips() {
+		let blit = |x, y, flip_x, flip_y| {
+			blit_sprite(Blit::new().with_dst(Rect::new(x, y, 3, 2)).flipped(flip_x, flip_y))
+		};
+		assert_eq!(blit(-1, 0, true, false), [[2, 1, 0, 0], [12, 11, 0, 0], [0; 4], [0; 4]]);
+		assert_eq!(blit(0, -1, false, true), [[1, 2, 3, 0], [0; 4], [0; 4], [0; 4]]);
+		assert_eq!(blit(1, 1, true, true), [[0; 4], [0, 13, 12, 11], [0, 3, 2, 1], [0; 4]]);
+	}
+
+	#[test]
+	fn color_key() {
+		let blit = Blit::new().with_dst(Rect::new(1, 1, 3, 2)).flipped(true, true).with_color_key(1);
+		assert_eq!(blit_sprite(blit), [[0; 4], [0, 13, 12, 11], [0, 3, 2, 0], [0; 4]]);
+
+		// Keyed pixels leave the destination untouched
+		let mut dst = Buffer::new_with_value(7u32, 4, 4);
+		dst.blit(&sprite(), &Blit::new().with_color_key(12));
+		assert_eq!(rows(&dst), [[1, 2, 3, 7], [11, 7, 13, 7], [7; 4], [7; 4]]);
+	}
+
+	#[test]
+	fn source_rect() {
+		let blit = Blit::new().with_src(Rect::new(1, 0, 2, 2));
+		assert_eq!(blit_sprite(blit), [[2, 3, 0, 0], [12, 13, 0, 0], [0; 4], [0; 4]]);
+
+		// Parts of the source rect outside the sprite aren't drawn
+		let blit = Blit::new().with_src(Rect::new(2, 1, 2, 2));
+		assert_eq!(blit_sprite(blit), [[13, 0, 0, 0], [0; 4], [0; 4], [0; 4]]);
+	}
+
+	#[test]
+	fn blend_modes() {
+		let mut src = Buffer::new(2, 1);
+		*src.get_mut(0, 0).unwrap() = 0.2f32;
+		*src.get_mut(1, 0).unwrap() = 0.8;
+
+		// Blending floats keeps the larger
+		let mut dst = Buffer::new_with_value(0.5f32, 3, 1);
+		dst.blit(&src, &Blit::new());
+		assert_eq!(rows(&dst), [[0.5, 0.8, 0.5]]);
+
+		let mut dst = Buffer::new_with_value(0.5f32, 3, 1);
+		dst.blit(&src, &Blit::new().with_blend_mode(BlendMode::Replace));
+		assert_eq!(rows(&dst), [[0.2, 0.8, 0.5]]);
+	}
+
+	#[test]
+	fn scaling() {
+		let blit = Blit::new().with_src(Rect::new(0, 0, 2, 1)).with_dst(Rect::new(0, 1, 4, 2));
+		assert_eq!(blit_sprite(blit), [[0; 4], [1, 1, 2, 2], [1, 1, 2, 2], [0; 4]]);
+
+		// Doubling then halving gives back the original
+		let mut big = Buffer::new(6, 4);
+		big.blit(&sprite(), &Blit::new().with_dst(Rect::new(0, 0, 6, 4)));
+		assert_eq!(rows(&big)[1], [1, 1, 2, 2, 3, 3]);
+		let mut small = Buffer::new(3, 2);
+		small.blit(&big, &Blit::new().with_dst(Rect::new(0, 0, 3, 2)));
+		assert_eq!(rows(&small), rows(&sprite()));
+
+		let mut ramp = Buffer::new(2, 1);
+		*ramp.get_mut(1, 0).unwrap() = 1.0f32;
+		let mut dst = Buffer::new(4, 1);
+		let blit = Blit::new()
+			.with_dst(Rect::new(0, 0, 4, 1))
+			.with_filter(TextureFilter::Bilinear)
+			.with_blend_mode(BlendMode::Replace);
+		dst.blit(&ramp, &blit);
+		assert_eq!(rows(&dst), [[0.0, 0.25, 0.75, 1.0]]);
+	}
+}
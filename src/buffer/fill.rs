@@ -0,0 +1,105 @@
+use super::{Blendable, Buffer, BufferRegion};
+use crate::{Color, Ditherer, Gradient};
+use nalgebra as na;
+use std::f32::consts::PI;
+
+/// Maps pixel positions onto a gradient. Coordinates are in pixels, relative to the filled region.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GradientShape {
+	/// Changes along the line from `start` (0.0) to `end` (1.0)
+	Linear { start: na::Point2<f32>, end: na::Point2<f32> },
+	/// Circles around `center`, reaching 1.0 at `radius`
+	Radial { center: na::Point2<f32>, radius: f32 },
+	/// Sweeps clockwise around `center`, starting at `angle` radians from the positive X axis
+	Conic { center: na::Point2<f32>, angle: f32 },
+	/// Like `Radial`, but with diamonds instead of circles
+	Diamond { center: na::Point2<f32>, radius: f32 },
+}
+
+impl GradientShape {
+	/// Vertical gradient from the top to the bottom of a region `height` pixels tall, e.g. for a sky
+	pub fn vertical(height: u32) -> Self {
+		GradientShape::Linear {
+			start: na::Point2::new(0.0, 0.0),
+			end: na::Point2::new(0.0, height as f32),
+		}
+	}
+
+	pub fn horizontal(width: u32) -> Self {
+		GradientShape::Linear {
+			start: na::Point2::new(0.0, 0.0),
+			end: na::Point2::new(width as f32, 0.0),
+		}
+	}
+
+	/// Gradient position of the point `x`, `y`. Not limited to `0.0..=1.0`, the gradient's spread
+	/// decides what happens outside that.
+	pub fn position(&self, x: f32, y: f32) -> f32 {
+		let p = na::Point2::new(x, y);
+		match self {
+			GradientShape::Linear { start, end } => {
+				let dir = end - start;
+				let len2 = dir.norm_squared();
+				if len2 == 0.0 {
+					0.0
+				} else {
+					(p - start).dot(&dir) / len2
+				}
+			}
+			GradientShape::Radial { center, radius } => (p - center).norm() / radius.max(f32::EPSILON),
+			GradientShape::Conic { center, angle } => {
+				let d = p - center;
+				((d.y.atan2(d.x) - angle) / (2.0 * PI)).rem_euclid(1.0)
+			}
+			GradientShape::Diamond { center, radius } => {
+				let d = p - center;
+				(d.x.abs() + d.y.abs()) / radius.max(f32::EPSILON)
+			}
+		}
+	}
+}
+
+impl<'a, T: Blendable> BufferRegion<'a, T> {
+	/// Replace every pixel in the region with a colour from `gradient`
+	pub fn fill_gradient(&mut self, gradient: &Gradient<T>, shape: &GradientShape) {
+		self.fill_with(|x, y| gradient.color(shape.position(x as f32 + 0.5, y as f32 + 0.5)));
+	}
+
+	// Set every pixel to the value returned for its position within the region
+	fn fill_with(&mut self, mut pixel: impl FnMut(i32, i32) -> T) {
+		let clip = self.local_clip();
+		for y in clip.y..clip.y + clip.height {
+			for x in clip.x..clip.x + clip.width {
+				if let Some(dst) = self.get_mut(x, y) {
+					*dst = pixel(x, y);
+				}
+			}
+		}
+	}
+}
+
+impl<'a> BufferRegion<'a, Color> {
+	/// Fill with a gradient, using an ordered `ditherer` to break up the bands between
+	/// neighbouring 8 bit colour levels
+	pub fn fill_gradient_dithered(&mut self, gradient: &Gradient<Color>, shape: &GradientShape, ditherer: &Ditherer) {
+		let (ox, oy) = (self.rect.x, self.rect.y);
+		self.fill_with(|x, y| {
+			let n = shape.position(x as f32 + 0.5, y as f32 + 0.5);
+			// Anchor the threshold map to the buffer, so neighbouring regions line up
+			let threshold = ditherer.threshold((x + ox) as u32, (y + oy) as u32);
+			gradient.color_dithered(n, threshold)
+		});
+	}
+}
+
+impl<T: Blendable> Buffer<T> {
+	pub fn fill_gradient(&mut self, gradient: &Gradient<T>, shape: &GradientShape) {
+		self.as_region_mut().fill_gradient(gradient, shape);
+	}
+}
+
+impl Buffer<Color> {
+	pub fn fill_gradient_dithered(&mut self, gradient: &Gradient<Color>, shape: &GradientShape, ditherer: &Ditherer) {
+		self.as_region_mut().fill_gradient_dithered(gradient, shape, ditherer);
+	}
+}
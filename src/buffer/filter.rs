@@ -0,0 +1,421 @@
+use super::{Blendable, Buffer};
+use crate::{linear_to_srgb, srgb_to_linear, Color, LinearColor, PremultipliedColor};
+use std::cmp::Ordering;
+
+/// Pixels which can be added together and scaled, as needed for convolution and resampling
+pub trait Filterable: Blendable {
+	/// Channels as values that can be averaged. Colour channels are multiplied by alpha, so
+	/// transparent pixels don't bleed into their neighbours.
+	fn to_floats(&self) -> [f32; 4];
+	/// Inverse of `to_floats`, clamping anything out of range
+	fn from_floats(floats: [f32; 4]) -> Self;
+
+	/// Like `to_floats`, but with sRGB encoded channels decoded to linear light, so averages match
+	/// how bright they look. The same as `to_floats` for types that aren't gamma encoded.
+	fn to_linear_floats(&self) -> [f32; 4] {
+		self.to_floats()
+	}
+
+	/// Inverse of `to_linear_floats`
+	fn from_linear_floats(floats: [f32; 4]) -> Self {
+		Self::from_floats(floats)
+	}
+}
+
+impl Filterable for f32 {
+	fn to_floats(&self) -> [f32; 4] {
+		[*self, 0.0, 0.0, 0.0]
+	}
+
+	fn from_floats(floats: [f32; 4]) -> Self {
+		floats[0]
+	}
+}
+
+impl Filterable for u8 {
+	fn to_floats(&self) -> [f32; 4] {
+		[*self as f32, 0.0, 0.0, 0.0]
+	}
+
+	fn from_floats(floats: [f32; 4]) -> Self {
+		floats[0].round().clamp(0.0, 255.0) as u8
+	}
+}
+
+// Straight alpha u8 channels to and from premultiplied floats
+fn premultiply(r: u8, g: u8, b: u8, a: u8) -> [f32; 4] {
+	premultiply_with(r, g, b, a, |v| v as f32)
+}
+
+fn unpremultiply(floats: [f32; 4]) -> (u8, u8, u8, u8) {
+	unpremultiply_with(floats, |v| v.round().clamp(0.0, 255.0) as u8)
+}
+
+// Same again, but with the RGB floats in linear light
+fn premultiply_linear(r: u8, g: u8, b: u8, a: u8) -> [f32; 4] {
+	premultiply_with(r, g, b, a, |v| srgb_to_linear(v as f32 / 255.0) * 255.0)
+}
+
+fn unpremultiply_linear(floats: [f32; 4]) -> (u8, u8, u8, u8) {
+	unpremultiply_with(floats, |v| (linear_to_srgb((v / 255.0).clamp(0.0, 1.0)) * 255.0).round() as u8)
+}
+
+fn premultiply_with(r: u8, g: u8, b: u8, a: u8, decode: impl Fn(u8) -> f32) -> [f32; 4] {
+	let a = a as f32 / 255.0;
+	[decode(r) * a, decode(g) * a, decode(b) * a, a]
+}
+
+fn unpremultiply_with(floats: [f32; 4], encode: impl Fn(f32) -> u8) -> (u8, u8, u8, u8) {
+	let a = floats[3].clamp(0.0, 1.0);
+	if a <= 0.0 {
+		return (0, 0, 0, 0);
+	}
+	let channel = |v: f32| encode(v / a);
+	(channel(floats[0]), channel(floats[1]), channel(floats[2]), (a * 255.0).round() as u8)
+}
+
+impl Filterable for Color {
+	fn to_floats(&self) -> [f32; 4] {
+		premultiply(self.r, self.g, self.b, self.a)
+	}
+
+	fn from_floats(floats: [f32; 4]) -> Self {
+		let (r, g, b, a) = unpremultiply(floats);
+		Color::rgba(r, g, b, a)
+	}
+
+	fn to_linear_floats(&self) -> [f32; 4] {
+		premultiply_linear(self.r, self.g, self.b, self.a)
+	}
+
+	fn from_linear_floats(floats: [f32; 4]) -> Self {
+		let (r, g, b, a) = unpremultiply_linear(floats);
+		Color::rgba(r, g, b, a)
+	}
+}
+
+#[cfg(feature = "mutunga")]
+impl Filterable for mutunga::Color {
+	fn to_floats(&self) -> [f32; 4] {
+		premultiply(self.r, self.g, self.b, self.a)
+	}
+
+	fn from_floats(floats: [f32; 4]) -> Self {
+		let (r, g, b, a) = unpremultiply(floats);
+		mutunga::Color::rgba(r, g, b, a)
+	}
+
+	fn to_linear_floats(&self) -> [f32; 4] {
+		premultiply_linear(self.r, self.g, self.b, self.a)
+	}
+
+	fn from_linear_floats(floats: [f32; 4]) -> Self {
+		let (r, g, b, a) = unpremultiply_linear(floats);
+		mutunga::Color::rgba(r, g, b, a)
+	}
+}
+
+impl Filterable for PremultipliedColor {
+	fn to_floats(&self) -> [f32; 4] {
+		[self.r as f32, self.g as f32, self.b as f32, self.a as f32]
+	}
+
+	fn from_floats(floats: [f32; 4]) -> Self {
+		let channel = |v: f32| v.round().clamp(0.0, 255.0) as u8;
+		PremultipliedColor::new(
+			channel(floats[0]),
+			channel(floats[1]),
+			channel(floats[2]),
+			channel(floats[3]),
+		)
+	}
+
+	fn to_linear_floats(&self) -> [f32; 4] {
+		Color::from(*self).to_linear_floats()
+	}
+
+	fn from_linear_floats(floats: [f32; 4]) -> Self {
+		Color::from_linear_floats(floats).into()
+	}
+}
+
+impl Filterable for LinearColor {
+	fn to_floats(&self) -> [f32; 4] {
+		[self.r * self.a, self.g * self.a, self.b * self.a, self.a]
+	}
+
+	fn from_floats(floats: [f32; 4]) -> Self {
+		let a = floats[3].clamp(0.0, 1.0);
+		if a <= 0.0 {
+			return LinearColor::default();
+		}
+		let channel = |v: f32| (v / a).max(0.0);
+		LinearColor::rgba(channel(floats[0]), channel(floats[1]), channel(floats[2]), a)
+	}
+}
+
+fn gaussian_kernel(sigma: f32) -> Vec<f32> {
+	let radius = (sigma * 3.0).ceil() as i32;
+	let kernel: Vec<f32> = (-radius..=radius)
+		.map(|x| (-(x * x) as f32 / (2.0 * sigma * sigma)).exp())
+		.collect();
+	let sum: f32 = kernel.iter().sum();
+	kernel.into_iter().map(|w| w / sum).collect()
+}
+
+// Convolve rows (or columns) with a centred 1D kernel, clamping at the edges
+fn convolve_axis(src: &[[f32; 4]], width: usize, height: usize, kernel: &[f32], vertical: bool) -> Vec<[f32; 4]> {
+	let radius = (kernel.len() / 2) as i32;
+	let mut dst = vec![[0.0; 4]; src.len()];
+	let max = if vertical { height } else { width } as i32 - 1;
+	for y in 0..height {
+		for x in 0..width {
+			let pos = if vertical { y } else { x } as i32;
+			let mut sum = [0.0; 4];
+			for (i, weight) in kernel.iter().enumerate() {
+				let p = (pos + i as i32 - radius).clamp(0, max) as usize;
+				let idx = if vertical { x + p * width } else { p + y * width };
+				for c in 0..4 {
+					sum[c] += src[idx][c] * weight;
+				}
+			}
+			dst[x + y * width] = sum;
+		}
+	}
+	dst
+}
+
+// Index of the pixel in `indices` with the lowest or highest luminance
+fn extreme<T: Blendable>(data: &[T], indices: impl Iterator<Item = usize>, ordering: Ordering) -> usize {
+	indices
+		.reduce(|best, idx| {
+			let cmp = data[idx].luminance().partial_cmp(&data[best].luminance());
+			if cmp == Some(ordering) {
+				idx
+			} else {
+				best
+			}
+		})
+		.unwrap_or(0)
+}
+
+impl<T: Blendable> Buffer<T> {
+	/// Replace each pixel with the median, by luminance, of the square `radius` pixels around it.
+	/// Removes speckles while keeping edges sharp.
+	pub fn median(&mut self, radius: u32) {
+		let (w, h) = (self.width as i32, self.height as i32);
+		let r = radius as i32;
+		let mut window = Vec::with_capacity(((2 * r + 1) * (2 * r + 1)) as usize);
+		let mut data = self.data.clone();
+		for y in 0..h {
+			for x in 0..w {
+				window.clear();
+				for wy in (y - r).max(0)..=(y + r).min(h - 1) {
+					for wx in (x - r).max(0)..=(x + r).min(w - 1) {
+						window.push(self.data[(wx + wy * w) as usize]);
+					}
+				}
+				let mid = window.len() / 2;
+				window.select_nth_unstable_by(mid, |a, b| {
+					a.luminance().partial_cmp(&b.luminance()).unwrap_or(Ordering::Equal)
+				});
+				data[(x + y * w) as usize] = window[mid];
+			}
+		}
+		self.data = data;
+	}
+
+	/// Grow bright areas, replacing each pixel with the brightest within `radius`
+	pub fn dilate(&mut self, radius: u32) {
+		self.morphology(radius, Ordering::Greater);
+	}
+
+	/// Grow dark areas, replacing each pixel with the darkest within `radius`
+	pub fn erode(&mut self, radius: u32) {
+		self.morphology(radius, Ordering::Less);
+	}
+
+	// Brightest or darkest in a square window. Done as two 1D passes, as a square is separable.
+	fn morphology(&mut self, radius: u32, ordering: Ordering) {
+		let (w, h) = (self.width as i32, self.height as i32);
+		let r = radius as i32;
+		let data = &self.data;
+		let rows: Vec<T> = (0..w * h)
+			.map(|i| {
+				let (x, y) = (i % w, i / w);
+				let row = ((x - r).max(0)..=(x + r).min(w - 1)).map(|wx| (wx + y * w) as usize);
+				data[extreme(data, row, ordering)]
+			})
+			.collect();
+		self.data = (0..w * h)
+			.map(|i| {
+				let (x, y) = (i % w, i / w);
+				let column = ((y - r).max(0)..=(y + r).min(h - 1)).map(|wy| (x + wy * w) as usize);
+				rows[extreme(&rows, column, ordering)]
+			})
+			.collect();
+	}
+
+	/// Sobel edge detection. Returns the magnitude of the luminance gradient at each pixel.
+	pub fn sobel(&self) -> Buffer<f32> {
+		let (w, h) = (self.width as i32, self.height as i32);
+		let mut edges = Buffer::new(self.width, self.height);
+		let lum = |x: i32, y: i32| self.data[(x.clamp(0, w - 1) + y.clamp(0, h - 1) * w) as usize].luminance();
+		for y in 0..h {
+			for x in 0..w {
+				let gx = lum(x + 1, y - 1) + 2.0 * lum(x + 1, y) + lum(x + 1, y + 1)
+					- lum(x - 1, y - 1)
+					- 2.0 * lum(x - 1, y)
+					- lum(x - 1, y + 1);
+				let gy = lum(x - 1, y + 1) + 2.0 * lum(x, y + 1) + lum(x + 1, y + 1)
+					- lum(x - 1, y - 1)
+					- 2.0 * lum(x, y - 1)
+					- lum(x + 1, y - 1);
+				edges.data[(x + y * w) as usize] = (gx * gx + gy * gy).sqrt();
+			}
+		}
+		edges
+	}
+}
+
+impl<T: Filterable> Buffer<T> {
+	/// Convolve with a 2D kernel made of a `horizontal` and `vertical` 1D kernel, each an odd
+	/// length and centred on the pixel. Pixels past the edges repeat the edge pixel.
+	pub fn convolve_separable(&mut self, horizontal: &[f32], vertical: &[f32]) {
+		if self.data.is_empty() {
+			return;
+		}
+		let (w, h) = (self.width as usize, self.height as usize);
+		let floats: Vec<_> = self.data.iter().map(Filterable::to_floats).collect();
+		let floats = convolve_axis(&floats, w, h, horizontal, false);
+		let floats = convolve_axis(&floats, w, h, vertical, true);
+		self.data = floats.into_iter().map(T::from_floats).collect();
+	}
+
+	/// Average of the square `radius` pixels around each pixel
+	pub fn box_blur(&mut self, radius: u32) {
+		if radius == 0 {
+			return;
+		}
+		let size = 2 * radius as usize + 1;
+		let kernel = vec![1.0 / size as f32; size];
+		self.convolve_separable(&kernel, &kernel);
+	}
+
+	/// Blur with a gaussian of standard deviation `sigma` pixels
+	pub fn gaussian_blur(&mut self, sigma: f32) {
+		if sigma.is_nan() || sigma <= 0.0 {
+			return;
+		}
+		let kernel = gaussian_kernel(sigma);
+		self.convolve_separable(&kernel, &kernel);
+	}
+
+	/// Unsharp mask: add `amount` times the difference between the image and a blurred copy
+	pub fn sharpen(&mut self, amount: f32) {
+		let mut blurred = self.clone();
+		blurred.gaussian_blur(1.0);
+		for (pixel, blurred) in self.data.iter_mut().zip(blurred.data.iter()) {
+			let (p, b) = (pixel.to_floats(), blurred.to_floats());
+			let mut sharp = [0.0; 4];
+			for c in 0..4 {
+				sharp[c] = p[c] + (p[c] - b[c]) * amount;
+			}
+			*pixel = T::from_floats(sharp);
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// A black buffer with the pixels at `points` set to 1.0
+	fn points(width: u32, height: u32, points: &[(i32, i32)]) -> Buffer<f32> {
+		let mut buffer = Buffer::new(width, height);
+		for (x, y) in points {
+			*buffer.get_mut(*x, *y).unwrap() = 1.0;
+		}
+		buffer
+	}
+
+	// Positions of the pixels that are 1.0
+	fn lit(buffer: &Buffer<f32>) -> Vec<(i32, i32)> {
+		let mut lit = vec![];
+		for y in 0..buffer.height() as i32 {
+			for x in 0..buffer.width() as i32 {
+				if *buffer.get(x, y).unwrap() == 1.0 {
+					lit.push((x, y));
+				}
+			}
+		}
+		lit
+	}
+
+	#[test]
+	fn gaussian_kernel_sums_to_one() {
+		for sigma in [0.3, 1.0, 2.5, 10.0] {
+			let kernel = gaussian_kernel(sigma);
+			assert_eq!(kernel.len(), 2 * (sigma * 3.0).ceil() as usize + 1);
+			assert!((kernel.iter().sum::<f32>() - 1.0).abs() < 1e-5, "sigma {}", sigma);
+		}
+	}
+
+	#[test]
+	fn blurring_constant_is_unchanged() {
+		let color = Color::rgba(100, 150, 200, 180);
+		let mut buffer = Buffer::new_with_value(color, 5, 4);
+		buffer.box_blur(2);
+		buffer.gaussian_blur(1.5);
+		buffer.sharpen(0.5);
+		assert!(buffer.as_slice().iter().all(|p| *p == color));
+
+		let mut floats = Buffer::new_with_value(0.25f32, 3, 7);
+		floats.gaussian_blur(4.0);
+		assert!(floats.as_slice().iter().all(|p| (p - 0.25).abs() < 1e-6));
+	}
+
+	#[test]
+	fn sobel_vertical_step() {
+		let mut buffer = Buffer::new(6, 4);
+		for y in 0..4 {
+			for x in 3..6 {
+				*buffer.get_mut(x, y).unwrap() = 1.0f32;
+			}
+		}
+		let edges = buffer.sobel();
+		for y in 0..4 {
+			let row: Vec<f32> = (0..6).map(|x| *edges.get(x, y).unwrap()).collect();
+			assert_eq!(row, [0.0, 0.0, 4.0, 4.0, 0.0, 0.0]);
+		}
+	}
+
+	#[test]
+	fn median_removes_outlier() {
+		let mut buffer = points(5, 5, &[(2, 2)]);
+		buffer.median(1);
+		assert!(lit(&buffer).is_empty());
+
+		// A solid block larger than the window survives
+		let block: Vec<_> = (0..5).flat_map(|y| (0..3).map(move |x| (x, y))).collect();
+		let mut buffer = points(5, 5, &block);
+		buffer.median(1);
+		assert_eq!(lit(&buffer), block);
+	}
+
+	#[test]
+	fn dilate_and_erode_single_pixel() {
+		let mut buffer = points(5, 5, &[(0, 4)]);
+		buffer.dilate(1);
+		assert_eq!(lit(&buffer), [(0, 3), (1, 3), (0, 4), (1, 4)]);
+
+		let mut buffer = points(5, 5, &[(2, 2)]);
+		buffer.dilate(1);
+		let square: Vec<_> = (1..4).flat_map(|y| (1..4).map(move |x| (x, y))).collect();
+		assert_eq!(lit(&buffer), square);
+		buffer.erode(1);
+		assert_eq!(lit(&buffer), [(2, 2)]);
+		buffer.erode(1);
+		assert!(lit(&buffer).is_empty());
+	}
+}
@@ -0,0 +1,191 @@
+use super::{Blendable, Buffer};
+use crate::geom::Rect;
+
+/// Which neighbouring pixels are considered connected
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Connectivity {
+	/// Only pixels sharing an edge
+	#[default]
+	Four,
+	/// Pixels sharing an edge or a corner
+	Eight,
+}
+
+impl Connectivity {
+	fn offsets(&self) -> &'static [(i32, i32)] {
+		match self {
+			Connectivity::Four => &[(1, 0), (-1, 0), (0, 1), (0, -1)],
+			Connectivity::Eight => &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, 1), (1, -1), (-1, -1)],
+		}
+	}
+}
+
+/// Group of connected, similar pixels found by `Buffer::label_regions`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectedRegion<T: Blendable> {
+	/// Value of this region's pixels in the label buffer
+	pub label: u32,
+	/// The first pixel found, which the rest of the region was compared against
+	pub pixel: T,
+	pub bounds: Rect,
+	/// Number of pixels in the region
+	pub count: usize,
+}
+
+// Equal, or with luminance within `tolerance` when it's above zero
+fn similar<T: Blendable>(a: &T, b: &T, tolerance: f32) -> bool {
+	a == b || (tolerance > 0.0 && (a.luminance() - b.luminance()).abs() <= tolerance)
+}
+
+impl<T: Blendable> Buffer<T> {
+	/// Paint bucket. Replaces the pixel at `x`, `y` and every pixel connected to it that is similar to
+	/// it with `pixel`. With a `tolerance` of zero only identical pixels are filled, otherwise pixels
+	/// whose luminance is within `tolerance` are too.
+	///
+	/// Returns the number of pixels filled.
+	pub fn flood_fill(&mut self, x: i32, y: i32, pixel: T, connectivity: Connectivity, tolerance: f32) -> usize {
+		let mut visited = vec![false; self.data.len()];
+		let filled = self.flood(x, y, connectivity, tolerance, &mut visited);
+		for idx in &filled {
+			self.data[*idx] = pixel;
+		}
+		filled.len()
+	}
+
+	/// Split the buffer into regions of connected, similar pixels, as compared by `flood_fill`.
+	/// Pixels equal to `T::default()` are treated as background and not part of any region.
+	///
+	/// Returns a buffer of each pixel's region label, 0 for the background, along with the regions.
+	/// Region labels start at 1 and match their position in the list plus 1.
+	pub fn label_regions(&self, connectivity: Connectivity, tolerance: f32) -> (Buffer<u32>, Vec<ConnectedRegion<T>>) {
+		let mut labels = Buffer::new(self.width, self.height);
+		let mut regions = vec![];
+		let mut visited: Vec<bool> = self.data.iter().map(|p| *p == T::default()).collect();
+		for start in 0..self.data.len() {
+			if visited[start] {
+				continue;
+			}
+			let (x, y) = (
+				(start % self.width as usize) as i32,
+				(start / self.width as usize) as i32,
+			);
+			let label = regions.len() as u32 + 1;
+			let (mut min_x, mut min_y, mut max_x, mut max_y) = (x, y, x, y);
+			let pixels = self.flood(x, y, connectivity, tolerance, &mut visited);
+			for idx in &pixels {
+				let (px, py) = ((idx % self.width as usize) as i32, (idx / self.width as usize) as i32);
+				min_x = min_x.min(px);
+				min_y = min_y.min(py);
+				max_x = max_x.max(px);
+				max_y = max_y.max(py);
+				labels.data[*idx] = label;
+			}
+			regions.push(ConnectedRegion {
+				label,
+				pixel: self.data[start],
+				bounds: Rect::new(min_x, min_y, max_x - min_x + 1, max_y - min_y + 1),
+				count: pixels.len(),
+			});
+		}
+		(labels, regions)
+	}
+
+	// Indices of the unvisited pixels connected to `x`, `y` that are similar to it, marking them visited
+	fn flood(&self, x: i32, y: i32, connectivity: Connectivity, tolerance: f32, visited: &mut [bool]) -> Vec<usize> {
+		let seed = match self.index(x, y) {
+			Some(idx) if !visited[idx] => idx,
+			_ => return vec![],
+		};
+		let target = self.data[seed];
+		let mut found = vec![];
+		let mut stack = vec![(x, y)];
+		visited[seed] = true;
+		while let Some((x, y)) = stack.pop() {
+			found.push(x as usize + y as usize * self.width as usize);
+			for (dx, dy) in connectivity.offsets() {
+				let (nx, ny) = (x + dx, y + dy);
+				if let Some(idx) = self.index(nx, ny) {
+					if !visited[idx] && similar(&self.data[idx], &target, tolerance) {
+						visited[idx] = true;
+						stack.push((nx, ny));
+					}
+				}
+			}
+		}
+		found
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Each row of digits is a row of pixels
+	fn buffer(rows: &[&str]) -> Buffer<u8> {
+		let mut buffer = Buffer::new(rows[0].len() as u32, rows.len() as u32);
+		for (y, row) in rows.iter().enumerate() {
+			for (x, c) in row.chars().enumerate() {
+				*buffer.get_mut(x as i32, y as i32).unwrap() = c.to_digit(10).unwrap() as u8;
+			}
+		}
+		buffer
+	}
+
+	const SHAPES: [&str; 5] = ["11000", "10020", "00200", "02003", "00033"];
+
+	#[test]
+	fn flood_fill() {
+		let mut shapes = buffer(&SHAPES);
+		assert_eq!(shapes.flood_fill(0, 0, 5, Connectivity::Four, 0.0), 3);
+		assert_eq!(shapes.as_slice()[..2], [5, 5]);
+		assert_eq!(shapes.clone().flood_fill(4, 0, 9, Connectivity::Four, 0.0), 16);
+		assert_eq!(shapes.flood_fill(-1, 0, 9, Connectivity::Four, 0.0), 0);
+
+		// The diagonal only splits the background with four connectivity
+		let diagonal = buffer(&["00001", "00010", "00100", "01000", "10000"]);
+		assert_eq!(diagonal.clone().flood_fill(0, 0, 9, Connectivity::Four, 0.0), 10);
+		assert_eq!(diagonal.clone().flood_fill(0, 0, 9, Connectivity::Eight, 0.0), 20);
+		assert_eq!(diagonal.clone().flood_fill(4, 4, 9, Connectivity::Four, 0.0), 10);
+		assert_eq!(diagonal.clone().flood_fill(4, 0, 9, Connectivity::Four, 0.0), 1);
+		assert_eq!(diagonal.clone().flood_fill(4, 0, 9, Connectivity::Eight, 0.0), 5);
+	}
+
+	#[test]
+	fn flood_fill_tolerance() {
+		let mut steps = buffer(&["0123", "4567"]);
+		// 0..=2 are within 2/255 of 0
+		assert_eq!(steps.clone().flood_fill(0, 0, 9, Connectivity::Four, 2.5 / 255.0), 3);
+		assert_eq!(steps.flood_fill(0, 0, 9, Connectivity::Four, 1.0), 8);
+	}
+
+	#[test]
+	fn label_regions() {
+		let (labels, regions) = buffer(&SHAPES).label_regions(Connectivity::Four, 0.0);
+		let summary: Vec<(u32, u8, Rect, usize)> =
+			regions.iter().map(|r| (r.label, r.pixel, r.bounds.clone(), r.count)).collect();
+		assert_eq!(
+			summary,
+			vec![
+				(1, 1, Rect::new(0, 0, 2, 2), 3),
+				(2, 2, Rect::new(3, 1, 1, 1), 1),
+				(3, 2, Rect::new(2, 2, 1, 1), 1),
+				(4, 2, Rect::new(1, 3, 1, 1), 1),
+				(5, 3, Rect::new(3, 3, 2, 2), 3),
+			]
+		);
+		assert_eq!(labels.as_slice()[..10], [1, 1, 0, 0, 0, 1, 0, 0, 2, 0]);
+
+		// The diagonal joins up
+		let (_, regions) = buffer(&SHAPES).label_regions(Connectivity::Eight, 0.0);
+		assert_eq!(regions.len(), 3);
+		assert_eq!((regions[1].bounds.clone(), regions[1].count), (Rect::new(1, 1, 3, 3), 3));
+	}
+
+	#[test]
+	fn label_ids() {
+		// Labels are drawn over whatever is underneath and never mixed
+		assert_eq!(8u32.blend(&7), 8);
+		assert_eq!(7u32.lerp(&8, 0.4), 7);
+		assert_eq!(7u32.lerp(&8, 0.6), 8);
+	}
+}
@@ -0,0 +1,205 @@
+use super::{Buffer, Filterable};
+use std::f32::consts::PI;
+
+/// Filter used when changing the size of a buffer
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ResampleFilter {
+	/// Blocky, but keeps hard edges and exact colours
+	Nearest,
+	#[default]
+	Bilinear,
+	/// Catmull-Rom spline, sharper than bilinear
+	Bicubic,
+	/// Three lobe Lanczos, the sharpest but may ring around hard edges
+	Lanczos,
+}
+
+impl ResampleFilter {
+	// Distance from the center beyond which `weight` is zero
+	fn support(&self) -> f32 {
+		match self {
+			ResampleFilter::Nearest => 0.5,
+			ResampleFilter::Bilinear => 1.0,
+			ResampleFilter::Bicubic => 2.0,
+			ResampleFilter::Lanczos => 3.0,
+		}
+	}
+
+	fn weight(&self, x: f32) -> f32 {
+		let x = x.abs();
+		match self {
+			ResampleFilter::Nearest => 1.0,
+			ResampleFilter::Bilinear => (1.0 - x).max(0.0),
+			ResampleFilter::Bicubic => {
+				if x < 1.0 {
+					1.5 * x * x * x - 2.5 * x * x + 1.0
+				} else if x < 2.0 {
+					-0.5 * x * x * x + 2.5 * x * x - 4.0 * x + 2.0
+				} else {
+					0.0
+				}
+			}
+			ResampleFilter::Lanczos => {
+				if x < 3.0 {
+					sinc(x) * sinc(x / 3.0)
+				} else {
+					0.0
+				}
+			}
+		}
+	}
+}
+
+fn sinc(x: f32) -> f32 {
+	if x == 0.0 {
+		1.0
+	} else {
+		(PI * x).sin() / (PI * x)
+	}
+}
+
+// Source pixels and their weights for each destination pixel along one axis
+fn contributions(src_len: u32, dst_len: u32, filter: ResampleFilter) -> Vec<Vec<(usize, f32)>> {
+	let scale = src_len as f32 / dst_len as f32;
+	// Widen the filter when shrinking, so every source pixel contributes
+	let stretch = scale.max(1.0);
+	let support = filter.support() * stretch;
+	(0..dst_len)
+		.map(|i| {
+			let center = (i as f32 + 0.5) * scale;
+			if filter == ResampleFilter::Nearest {
+				return vec![((center.floor() as usize).min(src_len as usize - 1), 1.0)];
+			}
+			let first = (center - support).floor() as i32;
+			let last = (center + support).ceil() as i32;
+			let mut weights: Vec<(usize, f32)> = (first..=last)
+				.map(|j| {
+					let idx = j.clamp(0, src_len as i32 - 1) as usize;
+					(idx, filter.weight((j as f32 + 0.5 - center) / stretch))
+				})
+				.filter(|(_, w)| *w != 0.0)
+				.collect();
+			let total: f32 = weights.iter().map(|(_, w)| w).sum();
+			if total != 0.0 {
+				weights.iter_mut().for_each(|(_, w)| *w /= total);
+			}
+			weights
+		})
+		.collect()
+}
+
+impl<T: Filterable> Buffer<T> {
+	/// Copy of the buffer scaled to `width` x `height`
+	pub fn resized(&self, width: u32, height: u32, filter: ResampleFilter) -> Buffer<T> {
+		let mut resized = Buffer::new(width, height);
+		if self.data.is_empty() || resized.data.is_empty() {
+			return resized;
+		}
+		if filter == ResampleFilter::Nearest {
+			let columns = contributions(self.width, width, filter);
+			let rows = contributions(self.height, height, filter);
+			for (y, row) in rows.iter().enumerate() {
+				for (x, column) in columns.iter().enumerate() {
+					resized.data[x + y * width as usize] = self.data[column[0].0 + row[0].0 * self.width as usize];
+				}
+			}
+			return resized;
+		}
+
+		// Horizontal pass into floats, then vertical pass into the result
+		let src_width = self.width as usize;
+		let floats: Vec<_> = self.data.iter().map(Filterable::to_floats).collect();
+		let columns = contributions(self.width, width, filter);
+		let mut horizontal = vec![[0.0f32; 4]; width as usize * self.height as usize];
+		for y in 0..self.height as usize {
+			for (x, column) in columns.iter().enumerate() {
+				let sum = &mut horizontal[x + y * width as usize];
+				for (idx, weight) in column {
+					let pixel = floats[idx + y * src_width];
+					for c in 0..4 {
+						sum[c] += pixel[c] * weight;
+					}
+				}
+			}
+		}
+		let rows = contributions(self.height, height, filter);
+		for (y, row) in rows.iter().enumerate() {
+			for x in 0..width as usize {
+				let mut sum = [0.0f32; 4];
+				for (idx, weight) in row {
+					let pixel = horizontal[x + idx * width as usize];
+					for c in 0..4 {
+						sum[c] += pixel[c] * weight;
+					}
+				}
+				resized.data[x + y * width as usize] = T::from_floats(sum);
+			}
+		}
+		resized
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Color;
+
+	const FILTERS: [ResampleFilter; 4] = [
+		ResampleFilter::Nearest,
+		ResampleFilter::Bilinear,
+		ResampleFilter::Bicubic,
+		ResampleFilter::Lanczos,
+	];
+
+	fn gradient(width: u32, height: u32) -> Buffer<Color> {
+		let mut buffer = Buffer::new(width, height);
+		for y in 0..height as i32 {
+			for x in 0..width as i32 {
+				*buffer.get_mut(x, y).unwrap() = Color::rgba((x * 40) as u8, (y * 30) as u8, ((x + y) * 7) as u8, 255);
+			}
+		}
+		buffer
+	}
+
+	#[test]
+	fn weights_sum_to_one() {
+		for filter in FILTERS {
+			for (src, dst) in [(5, 3), (3, 7), (4, 4), (1, 6), (9, 1)] {
+				for weights in contributions(src, dst, filter) {
+					let total: f32 = weights.iter().map(|(_, w)| w).sum();
+					assert!((total - 1.0).abs() < 1e-5, "{:?} {} -> {}", filter, src, dst);
+					assert!(weights.iter().all(|(idx, _)| *idx < src as usize));
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn same_size_is_identity() {
+		for filter in FILTERS {
+			for (w, h) in [(1, 6), (6, 1), (5, 4)] {
+				let buffer = gradient(w, h);
+				let resized = buffer.resized(w, h, filter);
+				assert_eq!(resized.size(), (w, h));
+				assert_eq!(resized.as_slice(), buffer.as_slice(), "{:?} {}x{}", filter, w, h);
+			}
+		}
+	}
+
+	#[test]
+	fn thin_buffers() {
+		for filter in FILTERS {
+			// Stretching a single row or column only changes the other axis
+			let column = Buffer::new_with_value(Color::rgb(10, 20, 30), 1, 4).resized(3, 4, filter);
+			assert!(column.as_slice().iter().all(|p| *p == Color::rgb(10, 20, 30)), "{:?}", filter);
+
+			let row = gradient(6, 1);
+			let resized = row.resized(6, 3, filter);
+			for y in 0..3 {
+				for x in 0..6 {
+					assert_eq!(resized.get(x, y), row.get(x, 0), "{:?}", filter);
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,371 @@
+use super::{Blendable, Buffer, BufferRegion};
+use nalgebra as na;
+use std::f32::consts::PI;
+
+// Vertical samples per pixel row when antialiasing. Horizontal coverage is calculated exactly.
+const AA_SAMPLES: usize = 4;
+
+// Maximum distance in pixels between a curve and the lines approximating it
+const CURVE_TOLERANCE: f32 = 0.2;
+
+/// Decides which parts of overlapping or self intersecting paths are inside
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+	/// Inside if the path winds around the point any number of times
+	#[default]
+	NonZero,
+	/// Inside if a ray from the point crosses the path an odd number of times, so overlaps become holes
+	EvenOdd,
+}
+
+/// How a shape is painted onto a buffer
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Paint<T: Blendable> {
+	pub pixel: T,
+	pub fill_rule: FillRule,
+	/// Blend edge pixels by how much of them the shape covers
+	pub antialias: bool,
+}
+
+impl<T: Blendable> Paint<T> {
+	pub fn new(pixel: T) -> Self {
+		Self {
+			pixel,
+			fill_rule: FillRule::NonZero,
+			antialias: false,
+		}
+	}
+
+	pub fn antialiased(mut self) -> Self {
+		self.antialias = true;
+		self
+	}
+
+	pub fn with_fill_rule(mut self, fill_rule: FillRule) -> Self {
+		self.fill_rule = fill_rule;
+		self
+	}
+}
+
+/// Outline of a 2D shape made of straight lines, in pixel coordinates.
+///
+/// Curves are flattened into short lines when the path is built.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+	subpaths: Vec<Subpath>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Subpath {
+	points: Vec<na::Point2<f32>>,
+	closed: bool,
+}
+
+impl Path {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Start a new subpath at `x`, `y`
+	pub fn move_to(&mut self, x: f32, y: f32) -> &mut Self {
+		self.subpaths.push(Subpath {
+			points: vec![na::Point2::new(x, y)],
+			closed: false,
+		});
+		self
+	}
+
+	pub fn line_to(&mut self, x: f32, y: f32) -> &mut Self {
+		match self.subpaths.last_mut() {
+			Some(subpath) if !subpath.closed => subpath.points.push(na::Point2::new(x, y)),
+			_ => {
+				self.move_to(x, y);
+			}
+		}
+		self
+	}
+
+	/// Join the end of the current subpath back to its start
+	pub fn close(&mut self) -> &mut Self {
+		if let Some(subpath) = self.subpaths.last_mut() {
+			subpath.closed = true;
+		}
+		self
+	}
+
+	/// Closed polygon through `points`
+	pub fn polygon(points: &[na::Point2<f32>]) -> Self {
+		let mut path = Self::polyline(points);
+		path.close();
+		path
+	}
+
+	/// Open line through `points`, for stroking
+	pub fn polyline(points: &[na::Point2<f32>]) -> Self {
+		Self {
+			subpaths: vec![Subpath {
+				points: points.to_vec(),
+				closed: false,
+			}],
+		}
+	}
+
+	pub fn circle(center: na::Point2<f32>, radius: f32) -> Self {
+		Self::ellipse(center, radius, radius)
+	}
+
+	pub fn ellipse(center: na::Point2<f32>, rx: f32, ry: f32) -> Self {
+		let mut path = Self::new();
+		path.push_arc(center, rx, ry, 0.0, 2.0 * PI);
+		path.close();
+		path
+	}
+
+	/// Open arc from `start` to `end` radians, measured clockwise from the positive X axis
+	pub fn arc(center: na::Point2<f32>, radius: f32, start: f32, end: f32) -> Self {
+		let mut path = Self::new();
+		path.push_arc(center, radius, radius, start, end);
+		path
+	}
+
+	/// Closed pie slice, like `arc` but joined to the center
+	pub fn pie(center: na::Point2<f32>, radius: f32, start: f32, end: f32) -> Self {
+		let mut path = Self::new();
+		path.move_to(center.x, center.y);
+		path.push_arc(center, radius, radius, start, end);
+		path.close();
+		path
+	}
+
+	pub fn rect(x: f32, y: f32, width: f32, height: f32) -> Self {
+		Self::polygon(&[
+			na::Point2::new(x, y),
+			na::Point2::new(x + width, y),
+			na::Point2::new(x + width, y + height),
+			na::Point2::new(x, y + height),
+		])
+	}
+
+	pub fn rounded_rect(x: f32, y: f32, width: f32, height: f32, radius: f32) -> Self {
+		let r = radius.min(width / 2.0).min(height / 2.0).max(0.0);
+		if r == 0.0 {
+			return Self::rect(x, y, width, height);
+		}
+		let mut path = Self::new();
+		path.push_arc(na::Point2::new(x + width - r, y + r), r, r, -PI / 2.0, 0.0);
+		path.push_arc(na::Point2::new(x + width - r, y + height - r), r, r, 0.0, PI / 2.0);
+		path.push_arc(na::Point2::new(x + r, y + height - r), r, r, PI / 2.0, PI);
+		path.push_arc(na::Point2::new(x + r, y + r), r, r, PI, PI * 1.5);
+		path.close();
+		path
+	}
+
+	/// Outline of the path drawn with a line `width` pixels wide, with round joins and caps
+	pub fn stroke(&self, width: f32) -> Self {
+		let r = width / 2.0;
+		let mut outline = Self::new();
+		if r <= 0.0 {
+			return outline;
+		}
+
+		for subpath in &self.subpaths {
+			let points = &subpath.points;
+			let mut segments: Vec<_> = points.windows(2).map(|w| (w[0], w[1])).collect();
+			if subpath.closed && points.len() > 2 {
+				segments.push((points[points.len() - 1], points[0]));
+			}
+
+			for (a, b) in segments {
+				let dir = b - a;
+				let len = dir.norm();
+				if len == 0.0 {
+					continue;
+				}
+				let normal = na::Vector2::new(-dir.y, dir.x) * (r / len);
+				outline.subpaths.push(Subpath {
+					points: vec![a + normal, b + normal, b - normal, a - normal],
+					closed: true,
+				});
+			}
+			for p in points {
+				outline.subpaths.extend(Self::circle(*p, r).subpaths);
+			}
+		}
+
+		// Every piece wound the same way, so a non-zero fill gives their union
+		for subpath in &mut outline.subpaths {
+			if signed_area(&subpath.points) < 0.0 {
+				subpath.points.reverse();
+			}
+		}
+		outline
+	}
+
+	// Append an elliptical arc to the current subpath, or start a new one
+	fn push_arc(&mut self, center: na::Point2<f32>, rx: f32, ry: f32, start: f32, end: f32) {
+		let sweep = end - start;
+		let radius = rx.abs().max(ry.abs());
+		// Number of segments keeping the midpoint of each within the tolerance of the curve
+		let step = if radius > CURVE_TOLERANCE {
+			2.0 * (1.0 - CURVE_TOLERANCE / radius).acos()
+		} else {
+			PI / 2.0
+		};
+		let count = ((sweep.abs() / step).ceil() as usize).clamp(1, 1024);
+		for i in 0..=count {
+			let angle = start + sweep * i as f32 / count as f32;
+			let (sin, cos) = angle.sin_cos();
+			self.line_to(center.x + rx * cos, center.y + ry * sin);
+		}
+	}
+}
+
+fn signed_area(points: &[na::Point2<f32>]) -> f32 {
+	let mut area = 0.0;
+	for i in 0..points.len() {
+		let (a, b) = (points[i], points[(i + 1) % points.len()]);
+		area += a.x * b.y - b.x * a.y;
+	}
+	area / 2.0
+}
+
+impl<'a, T: Blendable> BufferRegion<'a, T> {
+	/// Fill the inside of `path`. Every subpath is treated as closed.
+	pub fn fill_path(&mut self, path: &Path, paint: &Paint<T>) {
+		let samples = if paint.antialias { AA_SAMPLES } else { 1 };
+		let (w, h) = (self.rect.width.max(0) as usize, self.rect.height.max(0) as usize);
+
+		// Edges as (top, bottom, x at top, dx per y, winding direction)
+		let mut edges = vec![];
+		let (mut min_y, mut max_y) = (f32::INFINITY, f32::NEG_INFINITY);
+		for subpath in &path.subpaths {
+			let points = &subpath.points;
+			for i in 0..points.len() {
+				let (a, b) = (points[i], points[(i + 1) % points.len()]);
+				if a.y == b.y || !(a.x.is_finite() && a.y.is_finite() && b.x.is_finite() && b.y.is_finite()) {
+					continue;
+				}
+				let (top, bottom, dir) = if a.y < b.y { (a, b, 1) } else { (b, a, -1) };
+				edges.push((top.y, bottom.y, top.x, (bottom.x - top.x) / (bottom.y - top.y), dir));
+				min_y = min_y.min(top.y);
+				max_y = max_y.max(bottom.y);
+			}
+		}
+		if edges.is_empty() || w == 0 || h == 0 {
+			return;
+		}
+
+		let first_row = min_y.floor().max(0.0) as usize;
+		let last_row = (max_y.ceil().max(0.0) as usize).min(h);
+		let mut coverage = vec![0.0f32; w];
+		let mut crossings = vec![];
+		for y in first_row..last_row {
+			coverage.iter_mut().for_each(|c| *c = 0.0);
+			let (mut min_x, mut max_x) = (w, 0);
+
+			for s in 0..samples {
+				let sy = y as f32 + (s as f32 + 0.5) / samples as f32;
+				crossings.clear();
+				for (top, bottom, x, dx, dir) in &edges {
+					// Very wide or nearly horizontal edges can overflow `dx`, making the crossing NaN or infinite
+					let crossing = x + (sy - top) * dx;
+					if sy >= *top && sy < *bottom && crossing.is_finite() {
+						crossings.push((crossing, *dir));
+					}
+				}
+				crossings.sort_by(|l, r| l.0.total_cmp(&r.0));
+
+				let mut winding = 0;
+				for pair in crossings.windows(2) {
+					winding += pair[0].1;
+					let inside = match paint.fill_rule {
+						FillRule::NonZero => winding != 0,
+						FillRule::EvenOdd => winding % 2 != 0,
+					};
+					if !inside {
+						continue;
+					}
+					let (x0, x1) = (pair[0].0.max(0.0), pair[1].0.min(w as f32));
+					if x0 >= x1 {
+						continue;
+					}
+					if paint.antialias {
+						let weight = 1.0 / samples as f32;
+						let (first, last) = (x0.floor() as usize, (x1.ceil() as usize).min(w));
+						for (px, c) in coverage.iter_mut().enumerate().take(last).skip(first) {
+							let overlap = x1.min(px as f32 + 1.0) - x0.max(px as f32);
+							*c += overlap * weight;
+						}
+						min_x = min_x.min(first);
+						max_x = max_x.max(last);
+					} else {
+						// Pixels whose centers are inside the span
+						let first = (x0 - 0.5).ceil().max(0.0) as usize;
+						let last = ((x1 - 0.5).ceil().max(0.0) as usize).min(w);
+						coverage.iter_mut().take(last).skip(first).for_each(|c| *c = 1.0);
+						min_x = min_x.min(first);
+						max_x = max_x.max(last);
+					}
+				}
+			}
+
+			for (x, amount) in coverage.iter().enumerate().take(max_x).skip(min_x) {
+				if *amount <= 0.0 {
+					continue;
+				}
+				if let Some(dst) = self.get_mut(x as i32, y as i32) {
+					let blended = paint.pixel.blend(dst);
+					*dst = if *amount >= 1.0 {
+						blended
+					} else {
+						dst.lerp(&blended, *amount)
+					};
+				}
+			}
+		}
+	}
+
+	/// Draw the outline of `path` with a line `width` pixels wide
+	pub fn stroke_path(&mut self, path: &Path, width: f32, paint: &Paint<T>) {
+		let paint = Paint {
+			fill_rule: FillRule::NonZero,
+			..*paint
+		};
+		self.fill_path(&path.stroke(width), &paint);
+	}
+}
+
+impl<T: Blendable> Buffer<T> {
+	pub fn fill_path(&mut self, path: &Path, paint: &Paint<T>) {
+		self.as_region_mut().fill_path(path, paint);
+	}
+
+	pub fn stroke_path(&mut self, path: &Path, width: f32, paint: &Paint<T>) {
+		self.as_region_mut().stroke_path(path, width, paint);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fill_square() {
+		let mut buffer: Buffer<u8> = Buffer::new(8, 8);
+		let mut path = Path::new();
+		path.move_to(2.0, 2.0).line_to(6.0, 2.0).line_to(6.0, 5.0).line_to(2.0, 5.0).close();
+		buffer.fill_path(&path, &Paint::new(1));
+		assert_eq!(buffer.as_slice().iter().filter(|p| **p == 1).count(), 12);
+	}
+
+	#[test]
+	fn overflowing_edge() {
+		// The first edge's slope overflows to infinity, so its crossing on the row through its top is
+		// NaN, which used to panic when sorting crossings
+		let mut buffer: Buffer<u8> = Buffer::new(8, 8);
+		let mut path = Path::new();
+		path.move_to(-3e38, 0.5).line_to(3e38, 4.0).line_to(4.0, 7.0).close();
+		buffer.fill_path(&path, &Paint::new(1));
+		buffer.fill_path(&path, &Paint::new(1).antialiased());
+	}
+}
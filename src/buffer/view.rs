@@ -0,0 +1,177 @@
+use super::{Blendable, Buffer};
+use crate::geom::Rect;
+
+/// Read only view of a rectangle of a buffer, the immutable counterpart to `BufferRegion`.
+///
+/// Views are clipped to the buffer when they're created, so every row is a complete slice.
+#[derive(Clone, Debug)]
+pub struct BufferView<'a, T: Blendable> {
+	buffer: &'a Buffer<T>,
+	rect: Rect,
+}
+
+impl<'a, T: Blendable> BufferView<'a, T> {
+	/// View inside this one, offset by this view's position and clipped to its bounds
+	pub fn view(&self, rect: Rect) -> BufferView<'a, T> {
+		let rect = Rect::new(rect.x + self.rect.x, rect.y + self.rect.y, rect.width, rect.height);
+		BufferView {
+			buffer: self.buffer,
+			rect: rect.intersection(&self.rect).unwrap_or_default(),
+		}
+	}
+
+	/// Position and size within the buffer
+	pub fn rect(&self) -> &Rect {
+		&self.rect
+	}
+
+	pub fn width(&self) -> u32 {
+		self.rect.width as u32
+	}
+
+	pub fn height(&self) -> u32 {
+		self.rect.height as u32
+	}
+
+	pub fn size(&self) -> (u32, u32) {
+		(self.width(), self.height())
+	}
+
+	/// Pixel at `x`, `y` relative to the view
+	pub fn get(&self, x: i32, y: i32) -> Option<&'a T> {
+		if x < 0 || y < 0 || x >= self.rect.width || y >= self.rect.height {
+			return None;
+		}
+		self.buffer.get(x + self.rect.x, y + self.rect.y)
+	}
+
+	pub fn row(&self, y: u32) -> Option<&'a [T]> {
+		if y >= self.height() {
+			return None;
+		}
+		let x = self.rect.x as usize;
+		let row = self.buffer.row(y + self.rect.y as u32)?;
+		Some(&row[x..x + self.rect.width as usize])
+	}
+
+	pub fn rows(&self) -> impl Iterator<Item = &'a [T]> {
+		let view = self.clone();
+		(0..self.height()).filter_map(move |y| view.row(y))
+	}
+
+	/// Pixels of column `x`, top to bottom
+	pub fn column(&self, x: u32) -> Option<impl Iterator<Item = &'a T>> {
+		if x >= self.width() {
+			return None;
+		}
+		let x = x as usize;
+		Some(self.rows().map(move |row| &row[x]))
+	}
+
+	pub fn columns(&self) -> impl Iterator<Item = impl Iterator<Item = &'a T>> {
+		let view = self.clone();
+		(0..self.width()).filter_map(move |x| view.column(x))
+	}
+
+	/// Every pixel along with its `x` and `y` position in the view, row by row
+	pub fn enumerate_pixels(&self) -> impl Iterator<Item = (u32, u32, &'a T)> {
+		self.rows()
+			.enumerate()
+			.flat_map(|(y, row)| row.iter().enumerate().map(move |(x, p)| (x as u32, y as u32, p)))
+	}
+
+	/// Copy the viewed pixels into a new buffer
+	pub fn to_buffer(&self) -> Buffer<T> {
+		Buffer {
+			width: self.width(),
+			height: self.height(),
+			data: self.rows().flatten().copied().collect(),
+		}
+	}
+}
+
+impl<T: Blendable> Buffer<T> {
+	/// Read only view of `rect`, clipped to the buffer
+	pub fn view(&self, rect: Rect) -> BufferView<T> {
+		BufferView {
+			buffer: self,
+			rect: rect.intersection(&self.bounds()).unwrap_or_default(),
+		}
+	}
+
+	pub fn as_view(&self) -> BufferView<T> {
+		self.view(self.bounds())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// 4x3 buffer where each pixel is `10 * y + x`
+	fn numbered() -> Buffer<u32> {
+		let mut buffer = Buffer::new(4, 3);
+		for y in 0..3 {
+			for x in 0..4 {
+				*buffer.get_mut(x, y).unwrap() = (10 * y + x) as u32;
+			}
+		}
+		buffer
+	}
+
+	#[test]
+	fn empty_rows() {
+		let buffer: Buffer<u32> = Buffer::new(0, 3);
+		assert_eq!(buffer.rows().count(), 0);
+		assert_eq!(buffer.columns().count(), 0);
+		assert_eq!(buffer.enumerate_pixels().count(), 0);
+		assert_eq!(buffer.as_view().size(), (0, 0));
+		assert_eq!(buffer.as_view().rows().count(), 0);
+		assert_eq!(buffer.as_view().to_buffer().size(), (0, 0));
+	}
+
+	#[test]
+	fn columns() {
+		let buffer = numbered();
+		let columns: Vec<Vec<u32>> = buffer.columns().map(|c| c.copied().collect()).collect();
+		assert_eq!(columns, [[0, 10, 20], [1, 11, 21], [2, 12, 22], [3, 13, 23]]);
+		assert!(buffer.column(4).is_none());
+
+		let view = buffer.view(Rect::new(1, 1, 2, 2));
+		let columns: Vec<Vec<u32>> = view.columns().map(|c| c.copied().collect()).collect();
+		assert_eq!(columns, [[11, 21], [12, 22]]);
+		assert!(view.column(2).is_none());
+	}
+
+	#[test]
+	fn clipped_views() {
+		let buffer = numbered();
+		let view = buffer.view(Rect::new(-1, -2, 3, 4));
+		assert_eq!(view.rect(), &Rect::new(0, 0, 2, 2));
+		assert_eq!(view.get(1, 1), Some(&11));
+		assert_eq!(view.get(-1, 0), None);
+		assert_eq!(view.get(2, 0), None);
+
+		// Nested views are relative to their parent and clipped to it
+		let view = buffer.view(Rect::new(1, 1, 3, 2));
+		let nested = view.view(Rect::new(1, -1, 5, 5));
+		assert_eq!(nested.rect(), &Rect::new(2, 1, 2, 2));
+		assert_eq!(nested.get(0, 0), Some(&12));
+		let outside = view.view(Rect::new(-2, 0, 2, 1));
+		assert_eq!(outside.size(), (0, 0));
+		assert_eq!(outside.rows().count(), 0);
+
+		let copy = nested.to_buffer();
+		assert_eq!(copy.size(), (2, 2));
+		assert_eq!(copy.as_slice(), [12, 13, 22, 23]);
+		assert_eq!(outside.to_buffer().size(), (0, 0));
+	}
+
+	#[test]
+	#[should_panic(expected = "zipped buffers must be the same size")]
+	fn zip_size_mismatch() {
+		let colors: Buffer<u32> = Buffer::new(4, 3);
+		let depth: Buffer<f32> = Buffer::new(3, 4);
+		colors.zip(&depth).count();
+	}
+}
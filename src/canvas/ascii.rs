@@ -0,0 +1,162 @@
+use super::Cell;
+use crate::{Blendable, Buffer};
+
+pub const DEFAULT_ASCII_RAMP: &str = " .:-=+*#%@";
+
+/// Where edges are detected when picking line characters
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AsciiEdges {
+	/// Only use the density ramp
+	None,
+	/// Edges between changes in brightness
+	Luminance,
+	/// Edges between changes in depth, i.e. object silhouettes
+	Depth,
+}
+
+/// Maps pixels to characters of increasing density, for terminals without colour
+#[derive(Clone, Debug)]
+pub struct AsciiRamp {
+	/// Characters ordered from darkest to brightest
+	pub chars: Vec<char>,
+	pub edges: AsciiEdges,
+	/// Minimum gradient magnitude before a pixel is considered an edge
+	pub edge_threshold: f32,
+}
+
+impl Default for AsciiRamp {
+	fn default() -> Self {
+		Self::new(DEFAULT_ASCII_RAMP)
+	}
+}
+
+impl AsciiRamp {
+	pub fn new(chars: &str) -> Self {
+		Self {
+			chars: chars.chars().collect(),
+			edges: AsciiEdges::None,
+			edge_threshold: 0.5,
+		}
+	}
+
+	pub fn with_edges(mut self, edges: AsciiEdges, threshold: f32) -> Self {
+		self.edges = edges;
+		self.edge_threshold = threshold;
+		self
+	}
+
+	/// Character for a brightness in the range `0.0..=1.0`
+	pub fn symbol(&self, luminance: f32) -> char {
+		if self.chars.is_empty() {
+			return ' ';
+		}
+		let max = self.chars.len() - 1;
+		let idx = (luminance.clamp(0.0, 1.0) * max as f32).round() as usize;
+		self.chars[idx.min(max)]
+	}
+
+	/// Build the cell for the pixel at `x`, `y`. The `depth` buffer is only used with `AsciiEdges::Depth`
+	pub fn cell<O: Blendable>(
+		&self,
+		buffer: &Buffer<O>,
+		depth: Option<&Buffer<f32>>,
+		x: u32,
+		y: u32,
+	) -> Option<Cell<O>> {
+		let pixel = *buffer.get(x as i32, y as i32)?;
+		let edge = match (self.edges, depth) {
+			(AsciiEdges::Luminance, _) => self.edge_symbol(x, y, |x, y| buffer.get(x, y).map(|p| p.luminance())),
+			(AsciiEdges::Depth, Some(depth)) => self.edge_symbol(x, y, |x, y| {
+				depth.get(x, y).map(|d| if d.is_finite() { *d } else { 1.0 })
+			}),
+			_ => None,
+		};
+		let symbol = edge.unwrap_or_else(|| self.symbol(pixel.luminance()));
+
+		Some(Cell::new(symbol, pixel, O::default()))
+	}
+
+	// Run a Sobel operator over the samples around x, y, and choose a line
+	// character running perpendicular to the gradient
+	fn edge_symbol(&self, x: u32, y: u32, sample: impl Fn(i32, i32) -> Option<f32>) -> Option<char> {
+		let (x, y) = (x as i32, y as i32);
+		let center = sample(x, y)?;
+		let s = |dx: i32, dy: i32| sample(x + dx, y + dy).unwrap_or(center);
+
+		let gx = (s(1, -1) + 2.0 * s(1, 0) + s(1, 1)) - (s(-1, -1) + 2.0 * s(-1, 0) + s(-1, 1));
+		let gy = (s(-1, 1) + 2.0 * s(0, 1) + s(1, 1)) - (s(-1, -1) + 2.0 * s(0, -1) + s(1, -1));
+		if (gx * gx + gy * gy).sqrt() < self.edge_threshold {
+			return None;
+		}
+
+		// Direction of the edge in screen space (Y pointing down)
+		let (mut dx, mut dy) = (-gy, gx);
+		if dx < 0.0 {
+			dx = -dx;
+			dy = -dy;
+		}
+		let angle = dy.atan2(dx).to_degrees();
+		Some(if angle.abs() < 22.5 {
+			'-'
+		} else if angle.abs() > 67.5 {
+			'|'
+		} else if angle < 0.0 {
+			'/'
+		} else {
+			'\\'
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Color;
+
+	#[test]
+	fn ramp_ends() {
+		let ramp = AsciiRamp::default();
+		assert_eq!(ramp.symbol(0.0), ' ');
+		assert_eq!(ramp.symbol(1.0), '@');
+		assert_eq!(ramp.symbol(-1.0), ' ');
+		assert_eq!(ramp.symbol(2.0), '@');
+		assert_eq!(ramp.symbol(f32::NAN), ' ');
+	}
+
+	#[test]
+	fn custom_ramp() {
+		let ramp = AsciiRamp::new("ab");
+		assert_eq!(ramp.symbol(0.49), 'a');
+		assert_eq!(ramp.symbol(0.51), 'b');
+		assert_eq!(AsciiRamp::new("x").symbol(1.0), 'x');
+		assert_eq!(AsciiRamp::new("").symbol(1.0), ' ');
+	}
+
+	#[test]
+	fn edge_directions() {
+		let ramp = AsciiRamp::default().with_edges(AsciiEdges::Luminance, 0.5);
+		let step = |bright: fn(i32, i32) -> bool| ramp.edge_symbol(0, 0, |x, y| Some(bright(x, y) as u8 as f32));
+
+		assert_eq!(step(|x, _| x >= 0), Some('|'));
+		assert_eq!(step(|_, y| y >= 0), Some('-'));
+		// Y points down, so the boundary of x + y >= 0 runs from bottom left to top right
+		assert_eq!(step(|x, y| x + y >= 0), Some('/'));
+		assert_eq!(step(|x, y| x - y >= 0), Some('\\'));
+		assert_eq!(step(|_, _| true), None);
+	}
+
+	#[test]
+	fn luminance_edges_in_cells() {
+		let mut buffer = Buffer::new(4, 3);
+		buffer.fill(Color::black());
+		for y in 0..3 {
+			for x in 2..4 {
+				*buffer.get_mut(x, y).unwrap() = Color::white();
+			}
+		}
+		let ramp = AsciiRamp::default().with_edges(AsciiEdges::Luminance, 0.5);
+		let symbols: String = (0..4).map(|x| ramp.cell(&buffer, None, x, 1).unwrap().symbol).collect();
+		assert_eq!(symbols, " ||@");
+		assert!(ramp.cell(&buffer, None, 4, 1).is_none());
+	}
+}
@@ -0,0 +1,213 @@
+use crate::{Blendable, Buffer};
+
+const HALF_BLOCK: char = '▀';
+
+// Indexed by a bitmask of lit quadrants: top-left = 1, top-right = 2, bottom-left = 4, bottom-right = 8
+const QUADRANTS: [char; 16] = [
+	' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+// Braille dot bits, indexed by [y][x] inside the 2x4 cell
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+const BRAILLE_BASE: u32 = 0x2800;
+
+/// A single terminal cell, a symbol drawn in `fg` on top of `bg`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Cell<O: Blendable> {
+	pub fg: O,
+	pub bg: O,
+	pub symbol: char,
+}
+
+impl<O: Blendable> Cell<O> {
+	pub fn new(symbol: char, fg: O, bg: O) -> Self {
+		Self { fg, bg, symbol }
+	}
+}
+
+/// How pixels are packed into terminal cells
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CellMode {
+	/// 1x1 pixels per cell, drawn as the cell background
+	#[default]
+	Full,
+	/// 1x2 pixels per cell, using the upper half block
+	HalfBlock,
+	/// 2x2 pixels per cell, using the quadrant block characters
+	Quadrant,
+	/// 2x4 pixels per cell, using the Unicode Braille patterns
+	Braille,
+}
+
+impl CellMode {
+	/// Number of pixels covered by a single cell
+	pub fn cell_size(&self) -> (u32, u32) {
+		match self {
+			Self::Full => (1, 1),
+			Self::HalfBlock => (1, 2),
+			Self::Quadrant => (2, 2),
+			Self::Braille => (2, 4),
+		}
+	}
+
+	/// Size of the canvas needed to fill a terminal of `columns` x `rows` cells
+	pub fn canvas_size(&self, columns: u32, rows: u32) -> (u32, u32) {
+		let (w, h) = self.cell_size();
+		(columns * w, rows * h)
+	}
+
+	/// Number of whole cells that fit inside a buffer
+	pub fn cell_count<O: Blendable>(&self, buffer: &Buffer<O>) -> (u32, u32) {
+		let (w, h) = self.cell_size();
+		(buffer.width() / w, buffer.height() / h)
+	}
+
+	/// Build the cell at `column`, `row` from the pixels in `buffer`
+	pub fn cell<O: Blendable>(&self, buffer: &Buffer<O>, column: u32, row: u32) -> Option<Cell<O>> {
+		let (w, h) = self.cell_size();
+		let x = (column * w) as i32;
+		let y = (row * h) as i32;
+		match self {
+			Self::Full => {
+				let pixel = *buffer.get(x, y)?;
+				Some(Cell::new(' ', pixel, pixel))
+			}
+			Self::HalfBlock => {
+				let top = *buffer.get(x, y)?;
+				let bot = *buffer.get(x, y + 1)?;
+				Some(Cell::new(HALF_BLOCK, top, bot))
+			}
+			Self::Quadrant => {
+				let pixels = [
+					*buffer.get(x, y)?,
+					*buffer.get(x + 1, y)?,
+					*buffer.get(x, y + 1)?,
+					*buffer.get(x + 1, y + 1)?,
+				];
+				let (mask, fg, bg) = split_colors(&pixels);
+				Some(Cell::new(QUADRANTS[mask as usize], fg, bg))
+			}
+			Self::Braille => {
+				let mut pixels = [O::default(); 8];
+				for (i, pixel) in pixels.iter_mut().enumerate() {
+					*pixel = *buffer.get(x + (i % 2) as i32, y + (i / 2) as i32)?;
+				}
+				let (mask, fg, bg) = split_colors(&pixels);
+				if mask == 0 {
+					return Some(Cell::new(' ', fg, bg));
+				}
+				let mut bits = 0;
+				for (i, dots) in BRAILLE_DOTS.iter().enumerate() {
+					for (j, dot) in dots.iter().enumerate() {
+						if mask & (1 << (i * 2 + j)) != 0 {
+							bits |= dot;
+						}
+					}
+				}
+				let symbol = std::char::from_u32(BRAILLE_BASE + bits).unwrap_or(' ');
+				Some(Cell::new(symbol, fg, bg))
+			}
+		}
+	}
+}
+
+/// Split pixels into a foreground and a background group along the red, green
+/// or blue channel with the largest range, choosing the threshold that
+/// minimises the error within each group. Splitting by colour rather than
+/// luminance keeps e.g. red and green of similar brightness apart.
+///
+/// Returns a bitmask of the pixels in the foreground group, the brighter of the
+/// two, and the average colour of each group.
+fn split_colors<O: Blendable>(pixels: &[O]) -> (u32, O, O) {
+	let rgb: Vec<[f32; 3]> = pixels.iter().map(|p| p.to_rgb_floats()).collect();
+	let range = |c: usize| {
+		let values = rgb.iter().map(|v| v[c]);
+		values.clone().fold(f32::NEG_INFINITY, f32::max) - values.fold(f32::INFINITY, f32::min)
+	};
+	let (channel, widest) = (0..3).map(|c| (c, range(c))).fold((0, 0.0), |l, r| if r.1 > l.1 { r } else { l });
+
+	// Every pixel is the same colour, so it's all background
+	if widest.is_nan() || widest <= f32::EPSILON {
+		let bg = average(pixels.iter());
+		return (0, bg, bg);
+	}
+
+	let values: Vec<f32> = rgb.iter().map(|v| v[channel]).collect();
+	let mut sorted = values.clone();
+	sorted.sort_by(|l, r| l.total_cmp(r));
+
+	let mut best_threshold = sorted[0];
+	let mut best_error = f32::INFINITY;
+	for split in 1..sorted.len() {
+		if sorted[split] == sorted[split - 1] {
+			continue;
+		}
+		let error = variance(&sorted[..split]) + variance(&sorted[split..]);
+		if error < best_error {
+			best_error = error;
+			best_threshold = sorted[split - 1];
+		}
+	}
+
+	let mut mask = 0;
+	for (i, value) in values.iter().enumerate() {
+		if *value > best_threshold {
+			mask |= 1 << i;
+		}
+	}
+
+	let mut fg = average(pixels.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, p)| p));
+	let mut bg = average(pixels.iter().enumerate().filter(|(i, _)| mask & (1 << i) == 0).map(|(_, p)| p));
+	if fg.luminance() < bg.luminance() {
+		std::mem::swap(&mut fg, &mut bg);
+		mask ^= (1 << pixels.len()) - 1;
+	}
+
+	(mask, fg, bg)
+}
+
+fn variance(values: &[f32]) -> f32 {
+	let mean = values.iter().sum::<f32>() / values.len() as f32;
+	values.iter().map(|v| (v - mean) * (v - mean)).sum()
+}
+
+fn average<'a, O: Blendable + 'a>(pixels: impl Iterator<Item = &'a O>) -> O {
+	let mut avg = O::default();
+	for (i, pixel) in pixels.enumerate() {
+		if i == 0 {
+			avg = *pixel;
+		} else if *pixel != avg {
+			// Integer pixel types truncate when interpolating, so only mix in different pixels,
+			// otherwise a flat area would darken
+			avg = avg.lerp(pixel, 1.0 / (i + 1) as f32);
+		}
+	}
+	avg
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Color;
+
+	#[test]
+	fn split_by_colour() {
+		// Nearly the same luminance, so only their colour tells them apart
+		let (red, green) = (Color::rgb(200, 0, 0), Color::rgb(0, 60, 0));
+		let (mask, fg, bg) = split_colors(&[red, green, green, red]);
+		assert_eq!((mask, fg, bg), (0b0110, green, red));
+	}
+
+	#[test]
+	fn brighter_group_is_foreground() {
+		let (dark, light) = (Color::rgb(10, 10, 10), Color::rgb(250, 240, 230));
+		assert_eq!(split_colors(&[light, dark, dark, dark]), (0b0001, light, dark));
+		assert_eq!(split_colors(&[dark, light, light, light]), (0b1110, light, dark));
+	}
+
+	#[test]
+	fn flat() {
+		let grey = Color::rgb(128, 128, 128);
+		assert_eq!(split_colors(&[grey; 8]), (0, grey, grey));
+	}
+}
@@ -1,5 +1,12 @@
+mod ascii;
+pub use ascii::*;
+mod cell;
+pub use cell::*;
+
 use crate::mesh::{Line, Triangle};
-use crate::{Blendable, Buffer, Color, FragmentShader, Program, Varyings, Vertex, VertexShader};
+use crate::{
+	BitmapFont, Blendable, Buffer, Camera, Color, FragmentShader, Program, TextStyle, Varyings, Vertex, VertexShader,
+};
 use nalgebra as na;
 
 const DRAW_NORMALS: bool = false;
@@ -686,6 +693,30 @@ impl<O: Blendable> Canvas<O> {
 		}
 	}
 
+	/// Pack the canvas pixels into terminal cells, `callback` receives the column and row of each cell
+	pub fn draw_cells(&self, mode: CellMode, mut callback: impl FnMut(u32, u32, &Cell<O>)) {
+		let (columns, rows) = mode.cell_count(&self.buffer);
+		for row in 0..rows {
+			for column in 0..columns {
+				if let Some(cell) = mode.cell(&self.buffer, column, row) {
+					callback(column, row, &cell);
+				}
+			}
+		}
+	}
+
+	/// Convert each pixel into a character from an ASCII density ramp, `callback` receives one cell per pixel
+	pub fn draw_ascii(&self, ramp: &AsciiRamp, mut callback: impl FnMut(u32, u32, &Cell<O>)) {
+		let (w, h) = self.buffer.size();
+		for y in 0..h {
+			for x in 0..w {
+				if let Some(cell) = ramp.cell(&self.buffer, Some(&self.depth), x, y) {
+					callback(x, y, &cell);
+				}
+			}
+		}
+	}
+
 	pub fn context<'a>(&'a mut self) -> DrawContext<'a, O> {
 		DrawContext {
 			buffer: &mut self.buffer,
@@ -702,6 +733,10 @@ impl<O: Blendable> Canvas<O> {
 		&mut self.buffer
 	}
 
+	pub fn depth(&self) -> &Buffer<f32> {
+		&self.depth
+	}
+
 	pub fn with_transform<F: Fn(&mut Self)>(&mut self, transform: na::Matrix4<f32>, func: F) {
 		self.transform_stack.push(self.transform);
 		self.transform = self.transform * transform;
@@ -709,6 +744,54 @@ impl<O: Blendable> Canvas<O> {
 		self.transform = self.transform_stack.pop().unwrap();
 	}
 
+	/// Draw `text` anchored to a point in world space, as seen through `camera`. Pixels behind anything
+	/// already drawn are hidden. Returns `false` if the point is off screen or behind the camera.
+	pub fn draw_label(
+		&mut self,
+		camera: &impl Camera,
+		position: &na::Point3<f32>,
+		text: &str,
+		font: &BitmapFont,
+		style: &TextStyle<O>,
+	) -> bool {
+		let clip = camera.view_projection() * self.transform * position.to_homogeneous();
+		if clip.w <= 0.0 {
+			return false;
+		}
+		let p = clip.xyz() / clip.w;
+		if p.x.abs() > 1.0 || p.y.abs() > 1.0 || p.z.abs() > 1.0 {
+			return false;
+		}
+
+		let (w, h) = (self.buffer.width() as f32, self.buffer.height() as f32);
+		let x = (w * (p.x / 2.0 + 0.5)).round() as i32;
+		let y = (h - (h * (p.y / 2.0 + 0.5)).round() - 1.0) as i32;
+
+		let (buffer, depth) = (&mut self.buffer, &self.depth);
+		let mut plot = |x: i32, y: i32, pixel: &O| {
+			// Depth test, without writing so labels never hide each other
+			if !matches!(depth.get(x, y), Some(d) if p.z <= *d) {
+				return;
+			}
+			if let Some(dst) = buffer.get_mut(x, y) {
+				*dst = pixel.blend(dst);
+			}
+		};
+		if let Some(background) = &style.background {
+			for (left, top, line) in font.layout(text, x, y, style.align, style.vertical_align) {
+				for py in top..top + font.line_height() as i32 {
+					for px in left..left + font.line_width(line) as i32 {
+						plot(px, py, background);
+					}
+				}
+			}
+		}
+		font.rasterize(text, x, y, style.align, style.vertical_align, |x, y| {
+			plot(x, y, &style.color)
+		});
+		true
+	}
+
 	pub fn resize(&mut self, w: u32, h: u32) {
 		if self.buffer.width() == w && self.buffer.height() == h {
 			return;
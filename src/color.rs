@@ -1,6 +1,62 @@
+mod parse;
+pub use parse::*;
+mod premultiplied;
+pub use premultiplied::*;
+mod space;
+pub use space::*;
+mod tonemap;
+pub use tonemap::*;
+
 use crate::buffer::Blendable;
 
 pub type Ansi8BitColor = u8;
+pub type Ansi4BitColor = u8;
+
+// Channel levels of the xterm 6x6x6 colour cube
+pub(crate) const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// Default xterm values of the 16 base colours
+const ANSI_16: [(u8, u8, u8); 16] = [
+	(0, 0, 0),
+	(205, 0, 0),
+	(0, 205, 0),
+	(205, 205, 0),
+	(0, 0, 238),
+	(205, 0, 205),
+	(0, 205, 205),
+	(229, 229, 229),
+	(127, 127, 127),
+	(255, 0, 0),
+	(0, 255, 0),
+	(255, 255, 0),
+	(92, 92, 255),
+	(255, 0, 255),
+	(0, 255, 255),
+	(255, 255, 255),
+];
+
+/// RGB values of the full xterm 256 colour palette: the 16 base colours, the 6x6x6 colour cube
+/// and the 24 step greyscale ramp
+pub const XTERM_PALETTE: [Color; 256] = xterm_palette();
+
+const fn xterm_palette() -> [Color; 256] {
+	let mut palette = [Color { r: 0, g: 0, b: 0, a: 255 }; 256];
+	let mut i = 0;
+	while i < 256 {
+		let (r, g, b) = if i < 16 {
+			ANSI_16[i]
+		} else if i < 232 {
+			let n = i - 16;
+			(CUBE_LEVELS[n / 36], CUBE_LEVELS[n / 6 % 6], CUBE_LEVELS[n % 6])
+		} else {
+			let v = 8 + (i - 232) as u8 * 10;
+			(v, v, v)
+		};
+		palette[i] = Color { r, g, b, a: 255 };
+		i += 1;
+	}
+	palette
+}
 
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -90,12 +146,54 @@ impl Color {
 		}
 	}
 
+	/// Nearest colour in the xterm 256 colour palette, picking between the
+	/// colour cube and the greyscale ramp. The 16 base colours are skipped as
+	/// they vary between terminal themes.
 	pub fn as_8bit_ansi(&self) -> Ansi8BitColor {
-		let r = self.r / 51;
-		let g = self.g / 51;
-		let b = self.b / 51;
+		let ri = cube_index(self.r);
+		let gi = cube_index(self.g);
+		let bi = cube_index(self.b);
+		let cube = Color::rgb(CUBE_LEVELS[ri as usize], CUBE_LEVELS[gi as usize], CUBE_LEVELS[bi as usize]);
+
+		// Greyscale ramp goes from 8 to 238 in steps of 10
+		let avg = (self.r as u32 + self.g as u32 + self.b as u32) / 3;
+		let grey_idx = if avg < 8 { 0 } else { ((avg - 8 + 5) / 10).min(23) as u8 };
+		let grey = Color::grey(8 + grey_idx * 10);
 
-		16 + 36 * r + 6 * g + b
+		if grey.distance(self) < cube.distance(self) {
+			232 + grey_idx
+		} else {
+			16 + 36 * ri + 6 * gi + bi
+		}
+	}
+
+	/// Nearest of the 16 base ANSI colours, using the default xterm values
+	pub fn as_4bit_ansi(&self) -> Ansi4BitColor {
+		let mut best = 0;
+		let mut best_dist = f32::INFINITY;
+		for (i, (r, g, b)) in ANSI_16.iter().enumerate() {
+			let dist = Color::rgb(*r, *g, *b).distance(self);
+			if dist < best_dist {
+				best = i;
+				best_dist = dist;
+			}
+		}
+		best as Ansi4BitColor
+	}
+
+	/// RGB value of an xterm 256 colour palette index
+	pub fn from_8bit_ansi(index: Ansi8BitColor) -> Self {
+		XTERM_PALETTE[index as usize]
+	}
+
+	/// Perceptual distance between two colours, ignoring alpha. Uses the
+	/// "redmean" weighted euclidean distance.
+	pub fn distance(&self, other: &Color) -> f32 {
+		let rmean = (self.r as f32 + other.r as f32) / 2.0;
+		let dr = self.r as f32 - other.r as f32;
+		let dg = self.g as f32 - other.g as f32;
+		let db = self.b as f32 - other.b as f32;
+		((2.0 + rmean / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - rmean) / 256.0) * db * db).sqrt()
 	}
 
 	pub fn as_rgb(&self) -> (u8, u8, u8) {
@@ -116,12 +214,26 @@ impl Color {
 	}
 }
 
+fn cube_index(val: u8) -> u8 {
+	if val < 48 {
+		0
+	} else if val < 115 {
+		1
+	} else {
+		(val - 35) / 40
+	}
+}
+
 impl Blendable for Color {
 	fn blend(&self, bg: &Color) -> Color {
 		let (fg_r, fg_g, fg_b, fg_a) = self.as_floats();
 		let (bg_r, bg_g, bg_b, bg_a) = bg.as_floats();
 
 		let a = (1.0 - fg_a) * bg_a + fg_a;
+		// Both fully transparent, avoid dividing by zero
+		if a <= 0.0 {
+			return Color::transparent();
+		}
 		let r = ((1.0 - fg_a) * bg_a * bg_r + fg_a * fg_r) / a;
 		let g = ((1.0 - fg_a) * bg_a * bg_g + fg_a * fg_g) / a;
 		let b = ((1.0 - fg_a) * bg_a * bg_b + fg_a * fg_b) / a;
@@ -167,6 +279,16 @@ impl Blendable for Color {
 			Color::rgba(r, g, b, a)
 		}
 	}
+
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0]
+	}
+}
+
+impl From<[u8; 4]> for Color {
+	fn from([r, g, b, a]: [u8; 4]) -> Color {
+		Color::rgba(r, g, b, a)
+	}
 }
 
 #[cfg(feature = "mutunga")]
@@ -0,0 +1,348 @@
+use super::{Color, Hsl};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub struct ParseColorError(String);
+
+impl fmt::Display for ParseColorError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid color: {:?}", self.0)
+	}
+}
+
+impl Error for ParseColorError {}
+
+impl Color {
+	/// Colour of a CSS named colour, e.g. `"rebeccapurple"`
+	pub fn from_name(name: &str) -> Option<Color> {
+		let name = name.trim().to_lowercase();
+		CSS_COLORS
+			.binary_search_by_key(&name.as_str(), |(n, _)| n)
+			.ok()
+			.map(|i| {
+				let rgb = CSS_COLORS[i].1;
+				Color::rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+			})
+			.or_else(|| if name == "transparent" { Some(Color::transparent()) } else { None })
+	}
+
+	/// Format as `#rrggbb`, or `#rrggbbaa` when not fully opaque
+	pub fn to_hex(&self) -> String {
+		if self.a == 255 {
+			format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+		} else {
+			format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+		}
+	}
+}
+
+impl fmt::Display for Color {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(&self.to_hex())
+	}
+}
+
+/// Parses:
+///
+/// - Hex: `#f80`, `#f808`, `#ff8800`, `#ff880080`
+/// - Functions: `rgb(255, 136, 0)`, `rgba(100%, 50%, 0%, 0.5)`, `hsl(32, 100%, 50%)`, `hsla(32 100% 50% / 50%)`
+/// - CSS colour names: `orange`, `rebeccapurple`, `transparent`
+/// - xterm 256 colour palette indices: `208` or `ansi(208)`
+impl FromStr for Color {
+	type Err = ParseColorError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let err = || ParseColorError(s.to_string());
+		let input = s.trim().to_lowercase();
+
+		if let Some(hex) = input.strip_prefix('#') {
+			return parse_hex(hex).ok_or_else(err);
+		}
+		if let Ok(index) = input.parse::<u8>() {
+			return Ok(Color::from_8bit_ansi(index));
+		}
+		if let Some(color) = Color::from_name(&input) {
+			return Ok(color);
+		}
+
+		let open = input.find('(').ok_or_else(err)?;
+		let args = input[open + 1..].strip_suffix(')').ok_or_else(err)?;
+		let args: Vec<&str> = args
+			.split(|c: char| c == ',' || c == '/' || c.is_whitespace())
+			.filter(|a| !a.is_empty())
+			.collect();
+		let alpha = |args: &[&str]| match args.get(3) {
+			Some(a) => parse_unit(a).map(|a| (a * 255.0).round() as u8),
+			None => Some(255),
+		};
+
+		let color = match (input[..open].trim(), args.len()) {
+			("rgb" | "rgba", 3..=4) => {
+				let channel = |a: &str| match a.strip_suffix('%') {
+					Some(p) => p.parse::<f32>().ok().map(|p| p / 100.0 * 255.0),
+					None => a.parse::<f32>().ok(),
+				};
+				let c = args[..3]
+					.iter()
+					.map(|a| channel(a).map(|v| v.clamp(0.0, 255.0).round() as u8))
+					.collect::<Option<Vec<u8>>>();
+				match (c, alpha(&args)) {
+					(Some(c), Some(a)) => Some(Color::rgba(c[0], c[1], c[2], a)),
+					_ => None,
+				}
+			}
+			("hsl" | "hsla", 3..=4) => {
+				let h = args[0].strip_suffix("deg").unwrap_or(args[0]).parse::<f32>().ok();
+				let s = args[1].strip_suffix('%').and_then(|s| s.parse::<f32>().ok());
+				let l = args[2].strip_suffix('%').and_then(|l| l.parse::<f32>().ok());
+				match (h, s, l, alpha(&args)) {
+					(Some(h), Some(s), Some(l), Some(a)) => {
+						let mut color: Color = Hsl::new(h, s / 100.0, l / 100.0).into();
+						color.a = a;
+						Some(color)
+					}
+					_ => None,
+				}
+			}
+			("ansi", 1) => args[0].parse::<u8>().ok().map(Color::from_8bit_ansi),
+			_ => None,
+		};
+		color.ok_or_else(err)
+	}
+}
+
+fn parse_hex(hex: &str) -> Option<Color> {
+	if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+		return None;
+	}
+	let digit = |i: usize| u8::from_str_radix(&hex[i..i + 1], 16).ok().map(|v| v * 17);
+	let byte = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+	match hex.len() {
+		3 => Some(Color::rgb(digit(0)?, digit(1)?, digit(2)?)),
+		4 => Some(Color::rgba(digit(0)?, digit(1)?, digit(2)?, digit(3)?)),
+		6 => Some(Color::rgb(byte(0)?, byte(1)?, byte(2)?)),
+		8 => Some(Color::rgba(byte(0)?, byte(1)?, byte(2)?, byte(3)?)),
+		_ => None,
+	}
+}
+
+// Number in the range 0.0..=1.0, or a percentage
+fn parse_unit(val: &str) -> Option<f32> {
+	let val = match val.strip_suffix('%') {
+		Some(p) => p.parse::<f32>().ok()? / 100.0,
+		None => val.parse::<f32>().ok()?,
+	};
+	Some(val.clamp(0.0, 1.0))
+}
+
+// CSS Color Module Level 4 named colours, sorted by name
+const CSS_COLORS: [(&str, u32); 148] = [
+	("aliceblue", 0xf0f8ff),
+	("antiquewhite", 0xfaebd7),
+	("aqua", 0x00ffff),
+	("aquamarine", 0x7fffd4),
+	("azure", 0xf0ffff),
+	("beige", 0xf5f5dc),
+	("bisque", 0xffe4c4),
+	("black", 0x000000),
+	("blanchedalmond", 0xffebcd),
+	("blue", 0x0000ff),
+	("blueviolet", 0x8a2be2),
+	("brown", 0xa52a2a),
+	("burlywood", 0xdeb887),
+	("cadetblue", 0x5f9ea0),
+	("chartreuse", 0x7fff00),
+	("chocolate", 0xd2691e),
+	("coral", 0xff7f50),
+	("cornflowerblue", 0x6495ed),
+	("cornsilk", 0xfff8dc),
+	("crimson", 0xdc143c),
+	("cyan", 0x00ffff),
+	("darkblue", 0x00008b),
+	("darkcyan", 0x008b8b),
+	("darkgoldenrod", 0xb8860b),
+	("darkgray", 0xa9a9a9),
+	("darkgreen", 0x006400),
+	("darkgrey", 0xa9a9a9),
+	("darkkhaki", 0xbdb76b),
+	("darkmagenta", 0x8b008b),
+	("darkolivegreen", 0x556b2f),
+	("darkorange", 0xff8c00),
+	("darkorchid", 0x9932cc),
+	("darkred", 0x8b0000),
+	("darksalmon", 0xe9967a),
+	("darkseagreen", 0x8fbc8f),
+	("darkslateblue", 0x483d8b),
+	("darkslategray", 0x2f4f4f),
+	("darkslategrey", 0x2f4f4f),
+	("darkturquoise", 0x00ced1),
+	("darkviolet", 0x9400d3),
+	("deeppink", 0xff1493),
+	("deepskyblue", 0x00bfff),
+	("dimgray", 0x696969),
+	("dimgrey", 0x696969),
+	("dodgerblue", 0x1e90ff),
+	("firebrick", 0xb22222),
+	("floralwhite", 0xfffaf0),
+	("forestgreen", 0x228b22),
+	("fuchsia", 0xff00ff),
+	("gainsboro", 0xdcdcdc),
+	("ghostwhite", 0xf8f8ff),
+	("gold", 0xffd700),
+	("goldenrod", 0xdaa520),
+	("gray", 0x808080),
+	("green", 0x008000),
+	("greenyellow", 0xadff2f),
+	("grey", 0x808080),
+	("honeydew", 0xf0fff0),
+	("hotpink", 0xff69b4),
+	("indianred", 0xcd5c5c),
+	("indigo", 0x4b0082),
+	("ivory", 0xfffff0),
+	("khaki", 0xf0e68c),
+	("lavender", 0xe6e6fa),
+	("lavenderblush", 0xfff0f5),
+	("lawngreen", 0x7cfc00),
+	("lemonchiffon", 0xfffacd),
+	("lightblue", 0xadd8e6),
+	("lightcoral", 0xf08080),
+	("lightcyan", 0xe0ffff),
+	("lightgoldenrodyellow", 0xfafad2),
+	("lightgray", 0xd3d3d3),
+	("lightgreen", 0x90ee90),
+	("lightgrey", 0xd3d3d3),
+	("lightpink", 0xffb6c1),
+	("lightsalmon", 0xffa07a),
+	("lightseagreen", 0x20b2aa),
+	("lightskyblue", 0x87cefa),
+	("lightslategray", 0x778899),
+	("lightslategrey", 0x778899),
+	("lightsteelblue", 0xb0c4de),
+	("lightyellow", 0xffffe0),
+	("lime", 0x00ff00),
+	("limegreen", 0x32cd32),
+	("linen", 0xfaf0e6),
+	("magenta", 0xff00ff),
+	("maroon", 0x800000),
+	("mediumaquamarine", 0x66cdaa),
+	("mediumblue", 0x0000cd),
+	("mediumorchid", 0xba55d3),
+	("mediumpurple", 0x9370db),
+	("mediumseagreen", 0x3cb371),
+	("mediumslateblue", 0x7b68ee),
+	("mediumspringgreen", 0x00fa9a),
+	("mediumturquoise", 0x48d1cc),
+	("mediumvioletred", 0xc71585),
+	("midnightblue", 0x191970),
+	("mintcream", 0xf5fffa),
+	("mistyrose", 0xffe4e1),
+	("moccasin", 0xffe4b5),
+	("navajowhite", 0xffdead),
+	("navy", 0x000080),
+	("oldlace", 0xfdf5e6),
+	("olive", 0x808000),
+	("olivedrab", 0x6b8e23),
+	("orange", 0xffa500),
+	("orangered", 0xff4500),
+	("orchid", 0xda70d6),
+	("palegoldenrod", 0xeee8aa),
+	("palegreen", 0x98fb98),
+	("paleturquoise", 0xafeeee),
+	("palevioletred", 0xdb7093),
+	("papayawhip", 0xffefd5),
+	("peachpuff", 0xffdab9),
+	("peru", 0xcd853f),
+	("pink", 0xffc0cb),
+	("plum", 0xdda0dd),
+	("powderblue", 0xb0e0e6),
+	("purple", 0x800080),
+	("rebeccapurple", 0x663399),
+	("red", 0xff0000),
+	("rosybrown", 0xbc8f8f),
+	("royalblue", 0x4169e1),
+	("saddlebrown", 0x8b4513),
+	("salmon", 0xfa8072),
+	("sandybrown", 0xf4a460),
+	("seagreen", 0x2e8b57),
+	("seashell", 0xfff5ee),
+	("sienna", 0xa0522d),
+	("silver", 0xc0c0c0),
+	("skyblue", 0x87ceeb),
+	("slateblue", 0x6a5acd),
+	("slategray", 0x708090),
+	("slategrey", 0x708090),
+	("snow", 0xfffafa),
+	("springgreen", 0x00ff7f),
+	("steelblue", 0x4682b4),
+	("tan", 0xd2b48c),
+	("teal", 0x008080),
+	("thistle", 0xd8bfd8),
+	("tomato", 0xff6347),
+	("turquoise", 0x40e0d0),
+	("violet", 0xee82ee),
+	("wheat", 0xf5deb3),
+	("white", 0xffffff),
+	("whitesmoke", 0xf5f5f5),
+	("yellow", 0xffff00),
+	("yellowgreen", 0x9acd32),
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn parse(s: &str) -> Color {
+		s.parse().unwrap()
+	}
+
+	#[test]
+	fn names_are_sorted() {
+		// `from_name` binary searches the table
+		assert!(CSS_COLORS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+	}
+
+	#[test]
+	fn named() {
+		assert_eq!(parse("orange"), Color::rgb(255, 165, 0));
+		assert_eq!(parse(" RebeccaPurple "), Color::rgb(102, 51, 153));
+		assert_eq!(parse("aliceblue"), Color::rgb(240, 248, 255));
+		assert_eq!(parse("yellowgreen"), Color::rgb(154, 205, 50));
+		assert_eq!(parse("transparent"), Color::transparent());
+		assert!("notacolour".parse::<Color>().is_err());
+	}
+
+	#[test]
+	fn hex() {
+		assert_eq!(parse("#f80"), Color::rgb(255, 136, 0));
+		assert_eq!(parse("#f808"), Color::rgba(255, 136, 0, 136));
+		assert_eq!(parse("#FF8800"), Color::rgb(255, 136, 0));
+		assert_eq!(parse("#ff880080"), Color::rgba(255, 136, 0, 128));
+		for bad in ["#", "#ff", "#fffff", "#ggg", "#+1f", "#ééé"] {
+			assert!(bad.parse::<Color>().is_err(), "{}", bad);
+		}
+	}
+
+	#[test]
+	fn functional() {
+		assert_eq!(parse("rgb(255, 136, 0)"), Color::rgb(255, 136, 0));
+		assert_eq!(parse("rgba(100%, 50%, 0%, 0.5)"), Color::rgba(255, 128, 0, 128));
+		assert_eq!(parse("rgb(255 136 0 / 50%)"), Color::rgba(255, 136, 0, 128));
+		assert_eq!(parse("rgb(300, -5, 0)"), Color::rgb(255, 0, 0));
+		assert_eq!(parse("hsl(0, 100%, 50%)"), Color::rgb(255, 0, 0));
+		assert_eq!(parse("hsla(120deg 100% 25% / 0)"), Color::rgba(0, 128, 0, 0));
+		assert_eq!(parse("ansi(196)"), Color::from_8bit_ansi(196));
+		assert_eq!(parse("208"), Color::from_8bit_ansi(208));
+		for bad in ["rgb(1, 2)", "rgb(1, 2, 3", "hsl(0, 100, 50)", "rgb(a, b, c)", "cmyk(0, 0, 0, 0)", "256"] {
+			assert!(bad.parse::<Color>().is_err(), "{}", bad);
+		}
+	}
+
+	#[test]
+	fn hex_round_trip() {
+		for color in [Color::rgb(1, 2, 3), Color::rgba(250, 128, 0, 7)] {
+			assert_eq!(parse(&color.to_string()), color);
+		}
+	}
+}
@@ -0,0 +1,177 @@
+use super::Color;
+use crate::buffer::Blendable;
+
+// x * y / 255, rounded, without a division
+fn mul_255(x: u8, y: u8) -> u8 {
+	let v = x as u32 * y as u32 + 128;
+	((v + (v >> 8)) >> 8) as u8
+}
+
+/// Colour with its RGB channels already multiplied by alpha.
+///
+/// Blending is a single multiply-add per channel, and interpolating between transparent and opaque
+/// texels doesn't bleed the colour of invisible pixels, which is what bilinear filtering needs.
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PremultipliedColor {
+	pub r: u8,
+	pub g: u8,
+	pub b: u8,
+	pub a: u8,
+}
+
+impl PremultipliedColor {
+	/// Create from channels that are already premultiplied
+	pub fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+		Self {
+			r: r.min(a),
+			g: g.min(a),
+			b: b.min(a),
+			a,
+		}
+	}
+
+	/// Create from straight (not premultiplied) channels
+	pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+		Color::rgba(r, g, b, a).into()
+	}
+
+	pub fn rgb(r: u8, g: u8, b: u8) -> Self {
+		Self { r, g, b, a: 255 }
+	}
+
+	pub fn transparent() -> Self {
+		Self::default()
+	}
+}
+
+impl Blendable for PremultipliedColor {
+	fn blend(&self, bg: &PremultipliedColor) -> PremultipliedColor {
+		let inv = 255 - self.a;
+		PremultipliedColor {
+			r: self.r.saturating_add(mul_255(bg.r, inv)),
+			g: self.g.saturating_add(mul_255(bg.g, inv)),
+			b: self.b.saturating_add(mul_255(bg.b, inv)),
+			a: self.a.saturating_add(mul_255(bg.a, inv)),
+		}
+	}
+
+	fn set_brightness(&mut self, brightness: f32) {
+		self.r = (self.r as f32 * brightness).min(self.a as f32) as u8;
+		self.g = (self.g as f32 * brightness).min(self.a as f32) as u8;
+		self.b = (self.b as f32 * brightness).min(self.a as f32) as u8;
+	}
+
+	fn red() -> Self {
+		PremultipliedColor::rgb(255, 0, 0)
+	}
+
+	fn green() -> Self {
+		PremultipliedColor::rgb(0, 255, 0)
+	}
+
+	fn blue() -> Self {
+		PremultipliedColor::rgb(0, 0, 255)
+	}
+
+	fn lerp(&self, other: &Self, amount: f32) -> Self {
+		if amount <= 0.0 {
+			*self
+		} else if amount >= 1.0 {
+			*other
+		} else {
+			let inv = 1.0 - amount;
+			let mix = |l: u8, r: u8| (l as f32 * inv + r as f32 * amount).round() as u8;
+			PremultipliedColor {
+				r: mix(self.r, other.r),
+				g: mix(self.g, other.g),
+				b: mix(self.b, other.b),
+				a: mix(self.a, other.a),
+			}
+		}
+	}
+
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0]
+	}
+}
+
+impl From<Color> for PremultipliedColor {
+	fn from(color: Color) -> Self {
+		PremultipliedColor {
+			r: mul_255(color.r, color.a),
+			g: mul_255(color.g, color.a),
+			b: mul_255(color.b, color.a),
+			a: color.a,
+		}
+	}
+}
+
+impl From<PremultipliedColor> for Color {
+	fn from(color: PremultipliedColor) -> Self {
+		if color.a == 0 {
+			return Color::transparent();
+		}
+		let unmul = |val: u8| ((val as u32 * 255 + color.a as u32 / 2) / color.a as u32).min(255) as u8;
+		Color::rgba(unmul(color.r), unmul(color.g), unmul(color.b), color.a)
+	}
+}
+
+// Texture images are stored with straight alpha, so premultiply on import
+impl From<[u8; 4]> for PremultipliedColor {
+	fn from([r, g, b, a]: [u8; 4]) -> Self {
+		Color::rgba(r, g, b, a).into()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn multiply_by_alpha() {
+		for x in 0..=255 {
+			for y in 0..=255 {
+				assert_eq!(mul_255(x, y) as u32, (x as u32 * y as u32 * 2 + 255) / 510);
+			}
+		}
+	}
+
+	#[test]
+	fn round_trip() {
+		// Every premultiplied colour survives a round trip through straight alpha
+		for a in 0..=255 {
+			for v in 0..=a {
+				let color = PremultipliedColor::new(v, v / 2, a - v, a);
+				assert_eq!(PremultipliedColor::from(Color::from(color)), color);
+			}
+		}
+
+		// Straight colours lose precision as alpha drops, but opaque ones are exact
+		let opaque = Color::rgb(12, 200, 255);
+		assert_eq!(Color::from(PremultipliedColor::from(opaque)), opaque);
+		for v in 0..=255 {
+			let color = Color::from(PremultipliedColor::rgba(v, v, v, 128));
+			assert!((color.r as i32 - v as i32).abs() <= 1, "{} -> {}", v, color.r);
+			assert_eq!(color.a, 128);
+		}
+	}
+
+	#[test]
+	fn transparent_is_zero() {
+		assert_eq!(PremultipliedColor::rgba(200, 100, 50, 0), PremultipliedColor::transparent());
+		assert_eq!(PremultipliedColor::new(10, 20, 30, 0), PremultipliedColor::transparent());
+		assert_eq!(Color::from(PremultipliedColor::transparent()), Color::transparent());
+
+		let bg = PremultipliedColor::rgba(40, 80, 120, 200);
+		assert_eq!(PremultipliedColor::transparent().blend(&bg), bg);
+	}
+
+	#[test]
+	fn channels_clamped_to_alpha() {
+		assert_eq!(PremultipliedColor::new(255, 100, 50, 100), PremultipliedColor::new(100, 100, 50, 100));
+		let mut color = PremultipliedColor::new(100, 50, 0, 100);
+		color.set_brightness(3.0);
+		assert_eq!(color, PremultipliedColor::new(100, 100, 0, 100));
+	}
+}
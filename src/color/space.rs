@@ -0,0 +1,449 @@
+use super::Color;
+use crate::buffer::Blendable;
+
+/// Decode an sRGB channel in the range `0.0..=1.0` to linear light
+pub fn srgb_to_linear(val: f32) -> f32 {
+	if val <= 0.04045 {
+		val / 12.92
+	} else {
+		((val + 0.055) / 1.055).powf(2.4)
+	}
+}
+
+/// Encode a linear light channel in the range `0.0..=1.0` to sRGB
+pub fn linear_to_srgb(val: f32) -> f32 {
+	if val <= 0.003_130_8 {
+		val * 12.92
+	} else {
+		1.055 * val.powf(1.0 / 2.4) - 0.055
+	}
+}
+
+fn to_byte(val: f32) -> u8 {
+	(val.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+/// Colour with linear light RGB channels, usually in the range `0.0..=1.0`. Brighter values are
+/// clamped when converting to `Color`, use a `ToneMapper` to keep their detail.
+///
+/// Lighting and blending a `Canvas<LinearColor>` gives physically correct results, which are
+/// gamma encoded when converted back into a `Color`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LinearColor {
+	pub r: f32,
+	pub g: f32,
+	pub b: f32,
+	pub a: f32,
+}
+
+impl LinearColor {
+	pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+		Self::rgba(r, g, b, 1.0)
+	}
+
+	pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+		Self { r, g, b, a }
+	}
+}
+
+impl Blendable for LinearColor {
+	fn blend(&self, bg: &LinearColor) -> LinearColor {
+		let a = (1.0 - self.a) * bg.a + self.a;
+		if a <= 0.0 {
+			return LinearColor::default();
+		}
+		let mix = |fg: f32, bg_val: f32| ((1.0 - self.a) * bg.a * bg_val + self.a * fg) / a;
+		LinearColor::rgba(mix(self.r, bg.r), mix(self.g, bg.g), mix(self.b, bg.b), a)
+	}
+
+	fn set_brightness(&mut self, brightness: f32) {
+		self.r *= brightness;
+		self.g *= brightness;
+		self.b *= brightness;
+	}
+
+	fn red() -> Self {
+		LinearColor::rgb(1.0, 0.0, 0.0)
+	}
+
+	fn green() -> Self {
+		LinearColor::rgb(0.0, 1.0, 0.0)
+	}
+
+	fn blue() -> Self {
+		LinearColor::rgb(0.0, 0.0, 1.0)
+	}
+
+	fn lerp(&self, other: &Self, amount: f32) -> Self {
+		let amount = amount.clamp(0.0, 1.0);
+		let inv = 1.0 - amount;
+		LinearColor::rgba(
+			self.r * inv + other.r * amount,
+			self.g * inv + other.g * amount,
+			self.b * inv + other.b * amount,
+			self.a * inv + other.a * amount,
+		)
+	}
+
+	// Not clamped, so HDR colours keep their brightness above 1.0
+	fn to_rgb_floats(&self) -> [f32; 3] {
+		[self.r, self.g, self.b]
+	}
+}
+
+impl From<Color> for LinearColor {
+	fn from(color: Color) -> Self {
+		let (r, g, b, a) = color.as_floats();
+		LinearColor::rgba(srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), a)
+	}
+}
+
+// Texture images are sRGB encoded
+impl From<[u8; 4]> for LinearColor {
+	fn from([r, g, b, a]: [u8; 4]) -> Self {
+		Color::rgba(r, g, b, a).into()
+	}
+}
+
+impl From<LinearColor> for Color {
+	fn from(color: LinearColor) -> Self {
+		Color::rgba(
+			to_byte(linear_to_srgb(color.r)),
+			to_byte(linear_to_srgb(color.g)),
+			to_byte(linear_to_srgb(color.b)),
+			to_byte(color.a),
+		)
+	}
+}
+
+/// Hue (degrees), saturation and value
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Hsv {
+	pub h: f32,
+	pub s: f32,
+	pub v: f32,
+	pub a: f32,
+}
+
+/// Hue (degrees), saturation and lightness
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Hsl {
+	pub h: f32,
+	pub s: f32,
+	pub l: f32,
+	pub a: f32,
+}
+
+/// Perceptually uniform Oklab colour, with lightness `l` and opponent axes `a` (green/red) and `b` (blue/yellow)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Oklab {
+	pub l: f32,
+	pub a: f32,
+	pub b: f32,
+	pub alpha: f32,
+}
+
+/// Oklab in polar form: lightness, chroma and hue (degrees)
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Oklch {
+	pub l: f32,
+	pub c: f32,
+	pub h: f32,
+	pub alpha: f32,
+}
+
+impl Hsv {
+	pub fn new(h: f32, s: f32, v: f32) -> Self {
+		Self { h, s, v, a: 1.0 }
+	}
+}
+
+impl Hsl {
+	pub fn new(h: f32, s: f32, l: f32) -> Self {
+		Self { h, s, l, a: 1.0 }
+	}
+}
+
+impl Oklab {
+	pub fn new(l: f32, a: f32, b: f32) -> Self {
+		Self { l, a, b, alpha: 1.0 }
+	}
+}
+
+impl Oklch {
+	pub fn new(l: f32, c: f32, h: f32) -> Self {
+		Self { l, c, h, alpha: 1.0 }
+	}
+}
+
+// Hue in degrees and chroma of an RGB colour, shared by HSV and HSL
+fn hue_chroma(r: f32, g: f32, b: f32) -> (f32, f32, f32) {
+	let max = r.max(g).max(b);
+	let min = r.min(g).min(b);
+	let chroma = max - min;
+	let hue = if chroma == 0.0 {
+		0.0
+	} else if max == r {
+		60.0 * ((g - b) / chroma).rem_euclid(6.0)
+	} else if max == g {
+		60.0 * ((b - r) / chroma + 2.0)
+	} else {
+		60.0 * ((r - g) / chroma + 4.0)
+	};
+	(hue, chroma, max)
+}
+
+// RGB from hue in degrees, chroma and the amount to add to every channel
+fn from_hue_chroma(hue: f32, chroma: f32, offset: f32) -> (f32, f32, f32) {
+	let h = hue.rem_euclid(360.0) / 60.0;
+	let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+	let (r, g, b) = match h as u32 {
+		0 => (chroma, x, 0.0),
+		1 => (x, chroma, 0.0),
+		2 => (0.0, chroma, x),
+		3 => (0.0, x, chroma),
+		4 => (x, 0.0, chroma),
+		_ => (chroma, 0.0, x),
+	};
+	(r + offset, g + offset, b + offset)
+}
+
+impl From<Color> for Hsv {
+	fn from(color: Color) -> Self {
+		let (r, g, b, a) = color.as_floats();
+		let (h, chroma, v) = hue_chroma(r, g, b);
+		let s = if v == 0.0 { 0.0 } else { chroma / v };
+		Hsv { h, s, v, a }
+	}
+}
+
+impl From<Hsv> for Color {
+	fn from(hsv: Hsv) -> Self {
+		let v = hsv.v.clamp(0.0, 1.0);
+		let chroma = v * hsv.s.clamp(0.0, 1.0);
+		let (r, g, b) = from_hue_chroma(hsv.h, chroma, v - chroma);
+		Color::rgba(to_byte(r), to_byte(g), to_byte(b), to_byte(hsv.a))
+	}
+}
+
+impl From<Color> for Hsl {
+	fn from(color: Color) -> Self {
+		let (r, g, b, a) = color.as_floats();
+		let (h, chroma, max) = hue_chroma(r, g, b);
+		let l = max - chroma / 2.0;
+		let s = if l <= 0.0 || l >= 1.0 {
+			0.0
+		} else {
+			chroma / (1.0 - (2.0 * l - 1.0).abs())
+		};
+		Hsl { h, s, l, a }
+	}
+}
+
+impl From<Hsl> for Color {
+	fn from(hsl: Hsl) -> Self {
+		let l = hsl.l.clamp(0.0, 1.0);
+		let chroma = (1.0 - (2.0 * l - 1.0).abs()) * hsl.s.clamp(0.0, 1.0);
+		let (r, g, b) = from_hue_chroma(hsl.h, chroma, l - chroma / 2.0);
+		Color::rgba(to_byte(r), to_byte(g), to_byte(b), to_byte(hsl.a))
+	}
+}
+
+impl From<LinearColor> for Oklab {
+	fn from(c: LinearColor) -> Self {
+		let l = (0.412_221_47 * c.r + 0.536_332_55 * c.g + 0.051_445_995 * c.b).cbrt();
+		let m = (0.211_903_5 * c.r + 0.680_699_5 * c.g + 0.107_396_96 * c.b).cbrt();
+		let s = (0.088_302_46 * c.r + 0.281_718_85 * c.g + 0.629_978_7 * c.b).cbrt();
+		Oklab {
+			l: 0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+			a: 1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+			b: 0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+			alpha: c.a,
+		}
+	}
+}
+
+impl From<Oklab> for LinearColor {
+	fn from(c: Oklab) -> Self {
+		let l = (c.l + 0.396_337_78 * c.a + 0.215_803_76 * c.b).powi(3);
+		let m = (c.l - 0.105_561_346 * c.a - 0.063_854_17 * c.b).powi(3);
+		let s = (c.l - 0.089_484_18 * c.a - 1.291_485_5 * c.b).powi(3);
+		LinearColor::rgba(
+			4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+			-1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s,
+			-0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+			c.alpha,
+		)
+	}
+}
+
+impl From<Color> for Oklab {
+	fn from(color: Color) -> Self {
+		LinearColor::from(color).into()
+	}
+}
+
+impl From<Oklab> for Color {
+	fn from(oklab: Oklab) -> Self {
+		LinearColor::from(oklab).into()
+	}
+}
+
+impl From<Oklab> for Oklch {
+	fn from(c: Oklab) -> Self {
+		Oklch {
+			l: c.l,
+			c: (c.a * c.a + c.b * c.b).sqrt(),
+			h: c.b.atan2(c.a).to_degrees().rem_euclid(360.0),
+			alpha: c.alpha,
+		}
+	}
+}
+
+impl From<Oklch> for Oklab {
+	fn from(c: Oklch) -> Self {
+		let (sin, cos) = c.h.to_radians().sin_cos();
+		Oklab {
+			l: c.l,
+			a: c.c * cos,
+			b: c.c * sin,
+			alpha: c.alpha,
+		}
+	}
+}
+
+impl From<Color> for Oklch {
+	fn from(color: Color) -> Self {
+		Oklab::from(color).into()
+	}
+}
+
+impl From<Oklch> for Color {
+	fn from(oklch: Oklch) -> Self {
+		Oklab::from(oklch).into()
+	}
+}
+
+impl Color {
+	pub fn to_linear(&self) -> LinearColor {
+		(*self).into()
+	}
+
+	pub fn to_hsv(&self) -> Hsv {
+		(*self).into()
+	}
+
+	pub fn to_hsl(&self) -> Hsl {
+		(*self).into()
+	}
+
+	pub fn to_oklab(&self) -> Oklab {
+		(*self).into()
+	}
+
+	pub fn to_oklch(&self) -> Oklch {
+		(*self).into()
+	}
+
+	/// Interpolate in linear light, which avoids the dark bands of blending gamma encoded values
+	pub fn lerp_linear(&self, other: &Color, amount: f32) -> Color {
+		self.to_linear().lerp(&other.to_linear(), amount).into()
+	}
+
+	/// Interpolate in Oklab, for perceptually even gradients
+	pub fn lerp_oklab(&self, other: &Color, amount: f32) -> Color {
+		let (from, to) = (self.to_oklab(), other.to_oklab());
+		let t = amount.clamp(0.0, 1.0);
+		Oklab {
+			l: from.l + (to.l - from.l) * t,
+			a: from.a + (to.a - from.a) * t,
+			b: from.b + (to.b - from.b) * t,
+			alpha: from.alpha + (to.alpha - from.alpha) * t,
+		}
+		.into()
+	}
+
+	/// Scale the brightness in linear light rather than on the encoded values
+	pub fn set_brightness_linear(&mut self, brightness: f32) {
+		let mut linear = self.to_linear();
+		linear.set_brightness(brightness);
+		*self = linear.into();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Every combination of 0, 51, 102, 153, 204 and 255 in each channel
+	fn colors() -> impl Iterator<Item = Color> {
+		(0..216).map(|i| Color::rgb((i / 36 * 51) as u8, (i / 6 % 6 * 51) as u8, (i % 6 * 51) as u8))
+	}
+
+	fn assert_close(color: Color, expected: Color, tolerance: u8) {
+		let diff = |l: u8, r: u8| (l as i32 - r as i32).abs();
+		let max = diff(color.r, expected.r).max(diff(color.g, expected.g)).max(diff(color.b, expected.b));
+		assert!(max <= tolerance as i32, "{:?} != {:?}", color, expected);
+		assert_eq!(color.a, expected.a);
+	}
+
+	#[test]
+	fn srgb_transfer() {
+		assert_eq!(srgb_to_linear(0.0), 0.0);
+		assert!((srgb_to_linear(1.0) - 1.0).abs() < 1e-6);
+		assert!((srgb_to_linear(0.5) - 0.214_041).abs() < 1e-5);
+		assert!((linear_to_srgb(0.214_041) - 0.5).abs() < 1e-5);
+		for v in 0..=255 {
+			assert_eq!(to_byte(linear_to_srgb(srgb_to_linear(v as f32 / 255.0))), v);
+		}
+	}
+
+	#[test]
+	fn linear_round_trip() {
+		for color in colors() {
+			assert_eq!(Color::from(color.to_linear()), color);
+		}
+		let translucent = Color::rgba(10, 200, 30, 77);
+		assert_eq!(Color::from(translucent.to_linear()), translucent);
+	}
+
+	#[test]
+	fn oklab_reference_values() {
+		let close = |l: Oklab, r: Oklab| [l.l - r.l, l.a - r.a, l.b - r.b].iter().all(|d| d.abs() < 1e-3);
+		assert!(close(Color::white().to_oklab(), Oklab::new(1.0, 0.0, 0.0)));
+		assert!(close(Color::black().to_oklab(), Oklab::new(0.0, 0.0, 0.0)));
+		assert!(close(Color::rgb(255, 0, 0).to_oklab(), Oklab::new(0.627_955, 0.224_863, 0.125_846)));
+		assert!(close(Color::rgb(0, 0, 255).to_oklab(), Oklab::new(0.452_014, -0.032_457, -0.311_528)));
+
+		let red = Color::rgb(255, 0, 0).to_oklch();
+		assert!((red.c - 0.257_683).abs() < 1e-3);
+		assert!((red.h - 29.234).abs() < 0.1);
+	}
+
+	#[test]
+	fn oklab_round_trips() {
+		for color in colors() {
+			assert_close(color.to_oklab().into(), color, 1);
+			assert_close(color.to_oklch().into(), color, 1);
+		}
+	}
+
+	#[test]
+	fn hsv_hsl_reference_values() {
+		assert_eq!(Color::rgb(255, 0, 0).to_hsv(), Hsv::new(0.0, 1.0, 1.0));
+		assert_eq!(Color::rgb(0, 255, 255).to_hsv(), Hsv::new(180.0, 1.0, 1.0));
+		assert_eq!(Color::rgb(0, 0, 255).to_hsl(), Hsl::new(240.0, 1.0, 0.5));
+		assert_eq!(Color::white().to_hsl(), Hsl::new(0.0, 0.0, 1.0));
+		assert_eq!(Color::from(Hsv::new(330.0, 1.0, 1.0)), Color::rgb(255, 0, 128));
+		// Hues wrap around
+		assert_eq!(Color::from(Hsl::new(-120.0, 1.0, 0.5)), Color::rgb(0, 0, 255));
+	}
+
+	#[test]
+	fn hsv_hsl_round_trips() {
+		for color in colors() {
+			assert_close(color.to_hsv().into(), color, 0);
+			assert_close(color.to_hsl().into(), color, 0);
+		}
+	}
+}
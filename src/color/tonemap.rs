@@ -0,0 +1,105 @@
+use super::{linear_to_srgb, Color, LinearColor};
+use crate::Buffer;
+
+/// High dynamic range colour. Linear light channels aren't limited to `0.0..=1.0`, so bright
+/// lights can be accumulated without clipping, then tone mapped down for display.
+pub type HdrColor = LinearColor;
+
+/// Curve used to compress HDR values into the displayable range
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ToneMap {
+	/// Clip anything brighter than 1.0
+	Clamp,
+	/// `x / (1 + x)`
+	#[default]
+	Reinhard,
+	/// Reinhard, but values at the given white point map to 1.0 instead of approaching it
+	ReinhardExtended(f32),
+	/// Krzysztof Narkowicz's fit of the ACES filmic curve
+	Aces,
+}
+
+impl ToneMap {
+	/// Map a single linear channel into `0.0..=1.0`
+	pub fn map(&self, val: f32) -> f32 {
+		let val = val.max(0.0);
+		let mapped = match *self {
+			ToneMap::Clamp => val,
+			ToneMap::Reinhard => val / (1.0 + val),
+			ToneMap::ReinhardExtended(white) => {
+				let white = white.max(f32::EPSILON);
+				val * (1.0 + val / (white * white)) / (1.0 + val)
+			}
+			ToneMap::Aces => (val * (2.51 * val + 0.03)) / (val * (2.43 * val + 0.59) + 0.14),
+		};
+		mapped.clamp(0.0, 1.0)
+	}
+}
+
+/// Converts HDR buffers into displayable `Color` buffers
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ToneMapper {
+	pub tone_map: ToneMap,
+	/// Exposure adjustment in stops, each stop doubles the brightness
+	pub exposure: f32,
+}
+
+impl ToneMapper {
+	pub fn new(tone_map: ToneMap) -> Self {
+		Self {
+			tone_map,
+			exposure: 0.0,
+		}
+	}
+
+	pub fn with_exposure(mut self, exposure: f32) -> Self {
+		self.exposure = exposure;
+		self
+	}
+
+	pub fn map_color(&self, color: &HdrColor) -> Color {
+		let scale = self.exposure.exp2();
+		let channel = |val: f32| (linear_to_srgb(self.tone_map.map(val * scale)) * 255.0).round() as u8;
+		Color::rgba(
+			channel(color.r),
+			channel(color.g),
+			channel(color.b),
+			(color.a.clamp(0.0, 1.0) * 255.0).round() as u8,
+		)
+	}
+
+	pub fn apply(&self, buffer: &Buffer<HdrColor>) -> Buffer<Color> {
+		let (w, h) = buffer.size();
+		let mut colors = Buffer::new(w, h);
+		for y in 0..h as i32 {
+			for x in 0..w as i32 {
+				if let (Some(dst), Some(src)) = (colors.get_mut(x, y), buffer.get(x, y)) {
+					*dst = self.map_color(src);
+				}
+			}
+		}
+		colors
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Blendable;
+
+	#[test]
+	fn luminance_is_unclamped() {
+		assert_eq!(HdrColor::rgb(4.0, 4.0, 4.0).luminance(), 4.0);
+		assert!(HdrColor::rgb(8.0, 0.0, 0.0).luminance() > HdrColor::rgb(4.0, 0.0, 0.0).luminance());
+	}
+
+	#[test]
+	fn curves() {
+		for tone_map in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::ReinhardExtended(4.0), ToneMap::Aces] {
+			assert_eq!(tone_map.map(0.0), 0.0);
+			assert!(tone_map.map(100.0) <= 1.0);
+			assert!(tone_map.map(0.5) <= tone_map.map(0.6));
+		}
+		assert_eq!(ToneMap::ReinhardExtended(4.0).map(4.0), 1.0);
+	}
+}
@@ -0,0 +1,348 @@
+use crate::color::CUBE_LEVELS;
+use crate::{Buffer, Color, ColorDepth};
+
+const BLUE_NOISE_SIZE: usize = 32;
+const BLUE_NOISE_SIGMA: f32 = 1.5;
+
+/// Dithering applied when quantizing to a terminal palette
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Dither {
+	/// Snap each pixel to the nearest palette colour
+	#[default]
+	None,
+	/// Ordered dithering with a Bayer matrix of the given size (2, 4 or 8)
+	Bayer(u32),
+	/// Error diffusion. Looks best on still images, but the pattern changes
+	/// whenever anything on screen moves.
+	FloydSteinberg,
+	/// Ordered dithering with a blue noise threshold map
+	BlueNoise,
+}
+
+/// Quantizes buffers to a `ColorDepth` using a `Dither` method.
+///
+/// Ordered methods use a threshold map anchored to the pixel grid, so
+/// unchanged pixels produce identical output between frames.
+#[derive(Clone, Debug)]
+pub struct Ditherer {
+	method: Dither,
+	thresholds: Vec<f32>,
+	size: usize,
+}
+
+impl Default for Ditherer {
+	fn default() -> Self {
+		Self::new(Dither::None)
+	}
+}
+
+impl Ditherer {
+	pub fn new(method: Dither) -> Self {
+		let (thresholds, size) = match method {
+			Dither::Bayer(n) => {
+				let size = n.max(2).next_power_of_two().min(16) as usize;
+				(bayer_matrix(size), size)
+			}
+			Dither::BlueNoise => (blue_noise(BLUE_NOISE_SIZE), BLUE_NOISE_SIZE),
+			_ => (vec![], 0),
+		};
+
+		Self {
+			method,
+			thresholds,
+			size,
+		}
+	}
+
+	pub fn method(&self) -> Dither {
+		self.method
+	}
+
+	/// Threshold in the range `0.0..1.0` for the pixel at `x`, `y`
+	pub fn threshold(&self, x: u32, y: u32) -> f32 {
+		if self.size == 0 {
+			return 0.5;
+		}
+		let x = x as usize % self.size;
+		let y = y as usize % self.size;
+		self.thresholds[x + y * self.size]
+	}
+
+	/// Quantize every pixel in `buffer` to a colour that can be displayed at `depth`
+	pub fn apply(&self, buffer: &Buffer<Color>, depth: ColorDepth) -> Buffer<Color> {
+		let mut output = buffer.clone();
+		if depth == ColorDepth::TrueColor {
+			return output;
+		}
+
+		match self.method {
+			Dither::None => {
+				let (w, h) = buffer.size();
+				for y in 0..h {
+					for x in 0..w {
+						if let Some(pixel) = output.get_mut(x as i32, y as i32) {
+							*pixel = depth.quantize(pixel);
+						}
+					}
+				}
+			}
+			Dither::Bayer(_) | Dither::BlueNoise => {
+				let (w, h) = buffer.size();
+				for y in 0..h {
+					for x in 0..w {
+						if let Some(pixel) = output.get_mut(x as i32, y as i32) {
+							let spread = quantization_step(depth, pixel);
+							let bias = self.threshold(x, y) - 0.5;
+							let channel =
+								|v: u8, spread: f32| (v as f32 + bias * spread).round().clamp(0.0, 255.0) as u8;
+							let biased = Color::rgba(
+								channel(pixel.r, spread[0]),
+								channel(pixel.g, spread[1]),
+								channel(pixel.b, spread[2]),
+								pixel.a,
+							);
+							*pixel = depth.quantize(&biased);
+						}
+					}
+				}
+			}
+			Dither::FloydSteinberg => floyd_steinberg(&mut output, depth),
+		}
+
+		output
+	}
+}
+
+// Distance between the palette entries either side of `color` on each channel. The 256 colour
+// palette is uneven: its grey ramp is much finer than the colour cube, whose first step is largest.
+fn quantization_step(depth: ColorDepth, color: &Color) -> [f32; 3] {
+	match depth {
+		ColorDepth::TrueColor => [1.0; 3],
+		ColorDepth::Ansi256 if color.as_8bit_ansi() >= GREY_RAMP_START => [GREY_RAMP_STEP; 3],
+		ColorDepth::Ansi256 => [cube_step(color.r), cube_step(color.g), cube_step(color.b)],
+		ColorDepth::Ansi16 => [255.0 / 2.0; 3],
+		ColorDepth::Mono => [255.0; 3],
+	}
+}
+
+const GREY_RAMP_START: u8 = 232;
+const GREY_RAMP_STEP: f32 = 10.0;
+
+fn cube_step(value: u8) -> f32 {
+	CUBE_LEVELS
+		.windows(2)
+		.find(|pair| value <= pair[1])
+		.map_or(255.0 / 5.0, |pair| (pair[1] - pair[0]) as f32)
+}
+
+fn floyd_steinberg(buffer: &mut Buffer<Color>, depth: ColorDepth) {
+	let (w, h) = (buffer.width() as i32, buffer.height() as i32);
+	let mut errors = vec![[0.0f32; 3]; (w * h) as usize];
+	let idx = |x: i32, y: i32| (x + y * w) as usize;
+
+	for y in 0..h {
+		// Serpentine scan, to avoid the error drifting in one direction
+		let reverse = y % 2 == 1;
+		let dir = if reverse { -1 } else { 1 };
+		for i in 0..w {
+			let x = if reverse { w - 1 - i } else { i };
+			let pixel = match buffer.get_mut(x, y) {
+				Some(pixel) => pixel,
+				None => continue,
+			};
+			let err = errors[idx(x, y)];
+			let old = [
+				(pixel.r as f32 + err[0]).clamp(0.0, 255.0),
+				(pixel.g as f32 + err[1]).clamp(0.0, 255.0),
+				(pixel.b as f32 + err[2]).clamp(0.0, 255.0),
+			];
+			let new = depth.quantize(&Color::rgba(
+				old[0].round() as u8,
+				old[1].round() as u8,
+				old[2].round() as u8,
+				pixel.a,
+			));
+			*pixel = new;
+
+			let diff = [old[0] - new.r as f32, old[1] - new.g as f32, old[2] - new.b as f32];
+			for (dx, dy, weight) in &[(dir, 0, 7.0), (-dir, 1, 3.0), (0, 1, 5.0), (dir, 1, 1.0)] {
+				let (nx, ny) = (x + dx, y + dy);
+				if nx < 0 || nx >= w || ny >= h {
+					continue;
+				}
+				let e = &mut errors[idx(nx, ny)];
+				for c in 0..3 {
+					e[c] += diff[c] * weight / 16.0;
+				}
+			}
+		}
+	}
+}
+
+// Normalized Bayer matrix of `size` x `size`, built recursively
+fn bayer_matrix(size: usize) -> Vec<f32> {
+	let mut matrix = vec![0u32];
+	let mut n = 1;
+	while n < size {
+		let mut next = vec![0; n * n * 4];
+		for y in 0..n {
+			for x in 0..n {
+				let v = matrix[x + y * n] * 4;
+				next[x + y * n * 2] = v;
+				next[x + n + y * n * 2] = v + 2;
+				next[x + (y + n) * n * 2] = v + 3;
+				next[x + n + (y + n) * n * 2] = v + 1;
+			}
+		}
+		matrix = next;
+		n *= 2;
+	}
+
+	let count = (size * size) as f32;
+	matrix.iter().map(|v| (*v as f32 + 0.5) / count).collect()
+}
+
+// Blue noise threshold map using the void-and-cluster method. Uses a fixed
+// seed so the map is identical every run.
+fn blue_noise(size: usize) -> Vec<f32> {
+	let count = size * size;
+
+	// Gaussian energy falloff, wrapping around the edges so the map tiles
+	let mut kernel = vec![0.0; count];
+	for y in 0..size {
+		for x in 0..size {
+			let dx = x.min(size - x) as f32;
+			let dy = y.min(size - y) as f32;
+			kernel[x + y * size] = (-(dx * dx + dy * dy) / (2.0 * BLUE_NOISE_SIGMA * BLUE_NOISE_SIGMA)).exp();
+		}
+	}
+
+	let update = |energy: &mut Vec<f32>, pos: usize, sign: f32| {
+		let (px, py) = (pos % size, pos / size);
+		for y in 0..size {
+			for x in 0..size {
+				let kx = (x + size - px) % size;
+				let ky = (y + size - py) % size;
+				energy[x + y * size] += sign * kernel[kx + ky * size];
+			}
+		}
+	};
+	let tightest_cluster = |pattern: &[bool], energy: &[f32]| {
+		(0..count)
+			.filter(|i| pattern[*i])
+			.max_by(|l, r| energy[*l].partial_cmp(&energy[*r]).unwrap())
+			.unwrap()
+	};
+	let largest_void = |pattern: &[bool], energy: &[f32]| {
+		(0..count)
+			.filter(|i| !pattern[*i])
+			.min_by(|l, r| energy[*l].partial_cmp(&energy[*r]).unwrap())
+			.unwrap()
+	};
+
+	// Random initial pattern covering ~10% of the map
+	let mut seed: u32 = 0x2545_f491;
+	let mut pattern = vec![false; count];
+	let mut energy = vec![0.0; count];
+	let initial = count / 10;
+	let mut placed = 0;
+	while placed < initial {
+		seed ^= seed << 13;
+		seed ^= seed >> 17;
+		seed ^= seed << 5;
+		let pos = seed as usize % count;
+		if !pattern[pos] {
+			pattern[pos] = true;
+			update(&mut energy, pos, 1.0);
+			placed += 1;
+		}
+	}
+
+	// Spread the initial points out evenly
+	for _ in 0..count {
+		let cluster = tightest_cluster(&pattern, &energy);
+		pattern[cluster] = false;
+		update(&mut energy, cluster, -1.0);
+		let void = largest_void(&pattern, &energy);
+		pattern[void] = true;
+		update(&mut energy, void, 1.0);
+		if void == cluster {
+			break;
+		}
+	}
+
+	let mut ranks = vec![0; count];
+
+	// Rank the initial points by removing the tightest clusters first
+	let mut pat = pattern.clone();
+	let mut en = energy.clone();
+	for rank in (0..initial).rev() {
+		let cluster = tightest_cluster(&pat, &en);
+		pat[cluster] = false;
+		update(&mut en, cluster, -1.0);
+		ranks[cluster] = rank;
+	}
+
+	// Fill the remaining voids in order
+	for rank in initial..count {
+		let void = largest_void(&pattern, &energy);
+		pattern[void] = true;
+		update(&mut energy, void, 1.0);
+		ranks[void] = rank;
+	}
+
+	ranks.iter().map(|r| (*r as f32 + 0.5) / count as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Each threshold map should hold every rank exactly once
+	fn ranks(thresholds: &[f32]) -> Vec<usize> {
+		let count = thresholds.len() as f32;
+		let mut ranks: Vec<usize> = thresholds.iter().map(|t| (t * count - 0.5).round() as usize).collect();
+		ranks.sort_unstable();
+		ranks
+	}
+
+	#[test]
+	fn bayer_2x2() {
+		assert_eq!(bayer_matrix(2), vec![0.125, 0.625, 0.875, 0.375]);
+	}
+
+	#[test]
+	fn bayer_is_permutation() {
+		for size in [2, 4, 8, 16] {
+			assert_eq!(ranks(&bayer_matrix(size)), (0..size * size).collect::<Vec<_>>());
+		}
+	}
+
+	#[test]
+	fn blue_noise_is_permutation() {
+		let map = blue_noise(8);
+		assert_eq!(ranks(&map), (0..64).collect::<Vec<_>>());
+		assert_eq!(map, blue_noise(8));
+	}
+
+	#[test]
+	fn ansi256_spread() {
+		assert_eq!(
+			quantization_step(ColorDepth::Ansi256, &Color::rgb(100, 100, 100)),
+			[10.0; 3]
+		);
+		assert_eq!(
+			quantization_step(ColorDepth::Ansi256, &Color::rgb(50, 140, 250)),
+			[95.0, 40.0, 40.0]
+		);
+	}
+
+	#[test]
+	fn flat_colour_stays_flat() {
+		// Already exactly on a palette entry, so dithering shouldn't move it
+		let mut buffer = Buffer::new(8, 8);
+		buffer.fill(Color::rgb(135, 175, 215));
+		let dithered = Ditherer::new(Dither::Bayer(4)).apply(&buffer, ColorDepth::Ansi256);
+		assert!(dithered.as_slice().iter().all(|p| *p == Color::rgb(135, 175, 215)));
+	}
+}
@@ -0,0 +1,175 @@
+use crate::{Buffer, Canvas, Color};
+use image::gif::{GifEncoder, Repeat};
+use image::{ColorType, Delay, Frame, ImageFormat, RgbaImage};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+impl Buffer<Color> {
+	/// Save as an image, with the format chosen from the file extension, e.g. `.png`, `.ppm` or `.bmp`
+	pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+		let path = path.as_ref();
+		let format = ImageFormat::from_path(path)?;
+		self.save_with_format(path, format)
+	}
+
+	pub fn save_with_format(&self, path: impl AsRef<Path>, format: ImageFormat) -> Result<(), Box<dyn Error>> {
+		let path = path.as_ref();
+		log::debug!("Saving image: {} - {}x{}", path.display(), self.width(), self.height());
+		match format {
+			// PPM has no alpha channel
+			ImageFormat::Pnm => {
+				let rgb: Vec<u8> = self.as_slice().iter().flat_map(|p| [p.r, p.g, p.b]).collect();
+				image::save_buffer_with_format(path, &rgb, self.width(), self.height(), ColorType::Rgb8, format)?;
+			}
+			_ => {
+				image::save_buffer_with_format(
+					path,
+					self.as_bytes(),
+					self.width(),
+					self.height(),
+					ColorType::Rgba8,
+					format,
+				)?;
+			}
+		}
+		Ok(())
+	}
+
+	fn to_rgba_image(&self) -> RgbaImage {
+		RgbaImage::from_raw(self.width(), self.height(), self.as_bytes().to_vec())
+			.expect("Buffer size doesn't match its dimensions")
+	}
+}
+
+impl Canvas<Color> {
+	/// Save a screenshot of the canvas as a PNG
+	pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+		self.buffer().save_with_format(path, ImageFormat::Png)
+	}
+}
+
+/// Writes frames to an animated GIF as they're rendered
+pub struct GifWriter<W: Write> {
+	encoder: GifEncoder<W>,
+	delay: Delay,
+}
+
+impl GifWriter<BufWriter<File>> {
+	pub fn create(path: impl AsRef<Path>, frame_delay: Duration) -> Result<Self, Box<dyn Error>> {
+		let file = File::create(path)?;
+		Self::new(BufWriter::new(file), frame_delay)
+	}
+}
+
+impl<W: Write> GifWriter<W> {
+	/// Create an endlessly looping GIF, with `frame_delay` between each frame
+	pub fn new(out: W, frame_delay: Duration) -> Result<Self, Box<dyn Error>> {
+		let mut encoder = GifEncoder::new(out);
+		encoder.set_repeat(Repeat::Infinite)?;
+		Ok(Self {
+			encoder,
+			delay: Delay::from_saturating_duration(frame_delay),
+		})
+	}
+
+	pub fn push(&mut self, frame: &Buffer<Color>) -> Result<(), Box<dyn Error>> {
+		self.encoder
+			.encode_frame(Frame::from_parts(frame.to_rgba_image(), 0, 0, self.delay))?;
+		Ok(())
+	}
+}
+
+/// Save a sequence of frames as an endlessly looping animated GIF
+pub fn save_gif<'a>(
+	path: impl AsRef<Path>,
+	frames: impl IntoIterator<Item = &'a Buffer<Color>>,
+	frame_delay: Duration,
+) -> Result<(), Box<dyn Error>> {
+	let mut gif = GifWriter::create(path, frame_delay)?;
+	for frame in frames {
+		gif.push(frame)?;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::AnimationDecoder;
+	use std::path::PathBuf;
+
+	// A path in the system temp dir that's unique to this test run
+	fn temp_path(name: &str) -> PathBuf {
+		std::env::temp_dir().join(format!("toru-{}-{}", std::process::id(), name))
+	}
+
+	fn test_image() -> Buffer<Color> {
+		let mut buffer = Buffer::new(3, 2);
+		let colors = [
+			Color::rgb(255, 0, 0),
+			Color::rgb(0, 255, 0),
+			Color::rgb(0, 0, 255),
+			Color::rgba(10, 20, 30, 128),
+			Color::white(),
+			Color::rgba(0, 0, 0, 0),
+		];
+		for (i, color) in colors.iter().enumerate() {
+			*buffer.get_mut(i as i32 % 3, i as i32 / 3).unwrap() = *color;
+		}
+		buffer
+	}
+
+	#[test]
+	fn png_round_trip() {
+		let path = temp_path("round-trip.png");
+		let buffer = test_image();
+		buffer.save(&path).unwrap();
+		let loaded = image::open(&path).unwrap().to_rgba8();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(loaded.dimensions(), (3, 2));
+		assert_eq!(loaded.as_raw().as_slice(), buffer.as_bytes());
+	}
+
+	#[test]
+	fn ppm_drops_alpha() {
+		let path = temp_path("round-trip.ppm");
+		let buffer = test_image();
+		buffer.save(&path).unwrap();
+		let loaded = image::open(&path).unwrap().to_rgba8();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(loaded.dimensions(), (3, 2));
+		for (pixel, color) in loaded.pixels().zip(buffer.as_slice()) {
+			assert_eq!(pixel.0, [color.r, color.g, color.b, 255]);
+		}
+	}
+
+	#[test]
+	fn gif_frames() {
+		let path = temp_path("frames.gif");
+		let mut frames = vec![Buffer::new(4, 4), Buffer::new(4, 4), Buffer::new(4, 4)];
+		frames[0].fill(Color::rgb(255, 0, 0));
+		frames[1].fill(Color::rgb(0, 255, 0));
+		frames[2].fill(Color::rgb(0, 0, 255));
+		save_gif(&path, &frames, Duration::from_millis(50)).unwrap();
+
+		let file = File::open(&path).unwrap();
+		let decoded = image::gif::GifDecoder::new(std::io::BufReader::new(file))
+			.unwrap()
+			.into_frames()
+			.collect_frames()
+			.unwrap();
+		std::fs::remove_file(&path).unwrap();
+
+		assert_eq!(decoded.len(), 3);
+		for (frame, expected) in decoded.iter().zip(&frames) {
+			assert_eq!(frame.delay().numer_denom_ms(), (50, 1));
+			let Color { r, g, b, .. } = *expected.get(1, 1).unwrap();
+			assert_eq!(frame.buffer().get_pixel(1, 1).0, [r, g, b, 255]);
+		}
+	}
+}
@@ -0,0 +1,273 @@
+mod bdf;
+mod default;
+mod psf;
+
+use crate::buffer::{Blendable, Buffer, BufferRegion};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+#[derive(Debug)]
+pub struct FontError(String);
+
+impl fmt::Display for FontError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid font: {}", self.0)
+	}
+}
+
+impl Error for FontError {}
+
+/// Largest width or height of a glyph read from a font file
+pub const MAX_GLYPH_SIZE: u32 = 256;
+
+/// A single character's bitmap
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Glyph {
+	pub width: u32,
+	pub height: u32,
+	/// Position of the bitmap relative to the pen, with Y pointing down from the top of the line
+	pub x_offset: i32,
+	pub y_offset: i32,
+	/// How far to move the pen after drawing
+	pub advance: i32,
+	bitmap: Vec<bool>,
+}
+
+impl Glyph {
+	/// Create from a row major bitmap, `true` where pixels are set.
+	///
+	/// Panics if `width * height` doesn't fit in memory.
+	pub fn new(width: u32, height: u32, mut bitmap: Vec<bool>) -> Self {
+		let len = (width as usize).checked_mul(height as usize).expect("glyph bitmap too large");
+		bitmap.resize(len, false);
+		Self {
+			width,
+			height,
+			x_offset: 0,
+			y_offset: 0,
+			advance: width as i32,
+			bitmap,
+		}
+	}
+
+	pub fn is_set(&self, x: u32, y: u32) -> bool {
+		x < self.width && y < self.height && self.bitmap[x as usize + y as usize * self.width as usize]
+	}
+}
+
+/// Horizontal alignment of each line of text relative to the anchor point
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextAlign {
+	#[default]
+	Left,
+	Center,
+	Right,
+}
+
+/// Vertical alignment of a block of text relative to the anchor point
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum VerticalAlign {
+	#[default]
+	Top,
+	Middle,
+	Bottom,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TextStyle<T: Blendable> {
+	pub color: T,
+	/// Filled in behind each line of text
+	pub background: Option<T>,
+	pub align: TextAlign,
+	pub vertical_align: VerticalAlign,
+}
+
+impl<T: Blendable> TextStyle<T> {
+	pub fn new(color: T) -> Self {
+		Self {
+			color,
+			background: None,
+			align: TextAlign::Left,
+			vertical_align: VerticalAlign::Top,
+		}
+	}
+
+	pub fn with_background(mut self, background: T) -> Self {
+		self.background = Some(background);
+		self
+	}
+
+	pub fn with_align(mut self, align: TextAlign, vertical_align: VerticalAlign) -> Self {
+		self.align = align;
+		self.vertical_align = vertical_align;
+		self
+	}
+}
+
+/// Fixed size bitmap font. `BitmapFont::default()` is a built in 5x7 pixel ASCII font.
+#[derive(Clone, Debug)]
+pub struct BitmapFont {
+	glyphs: HashMap<char, Glyph>,
+	line_height: u32,
+}
+
+impl Default for BitmapFont {
+	fn default() -> Self {
+		let mut font = Self::new(default::GLYPH_HEIGHT + 1);
+		for (ch, rows) in default::GLYPHS.iter() {
+			let mut bitmap = Vec::with_capacity((default::GLYPH_WIDTH * default::GLYPH_HEIGHT) as usize);
+			for row in rows {
+				for x in (0..default::GLYPH_WIDTH).rev() {
+					bitmap.push(row & (1 << x) != 0);
+				}
+			}
+			let mut glyph = Glyph::new(default::GLYPH_WIDTH, default::GLYPH_HEIGHT, bitmap);
+			glyph.advance += 1;
+			font.insert(*ch, glyph);
+		}
+		font
+	}
+}
+
+impl BitmapFont {
+	pub fn new(line_height: u32) -> Self {
+		Self {
+			glyphs: HashMap::new(),
+			line_height,
+		}
+	}
+
+	/// Load a BDF or PSF font file
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+		let path = path.as_ref();
+		log::debug!("Loading font: {}", path.display());
+		let data = std::fs::read(path)?;
+		if data.starts_with(&psf::PSF1_MAGIC) || data.starts_with(&psf::PSF2_MAGIC) {
+			Ok(Self::from_psf(&data)?)
+		} else {
+			Ok(Self::from_bdf(std::str::from_utf8(&data)?)?)
+		}
+	}
+
+	pub fn from_bdf(source: &str) -> Result<Self, FontError> {
+		bdf::parse(source)
+	}
+
+	pub fn from_psf(data: &[u8]) -> Result<Self, FontError> {
+		psf::parse(data)
+	}
+
+	pub fn insert(&mut self, ch: char, glyph: Glyph) {
+		self.glyphs.insert(ch, glyph);
+	}
+
+	/// Glyph for `ch`, falling back to `?` for characters missing from the font
+	pub fn glyph(&self, ch: char) -> Option<&Glyph> {
+		self.glyphs.get(&ch).or_else(|| self.glyphs.get(&'?'))
+	}
+
+	pub fn line_height(&self) -> u32 {
+		self.line_height
+	}
+
+	pub fn set_line_height(&mut self, line_height: u32) {
+		self.line_height = line_height;
+	}
+
+	/// Width in pixels of a single line of text
+	pub fn line_width(&self, line: &str) -> u32 {
+		line.chars().filter_map(|ch| self.glyph(ch)).map(|g| g.advance).sum::<i32>().max(0) as u32
+	}
+
+	/// Width and height of a block of text, which may span multiple lines
+	pub fn text_size(&self, text: &str) -> (u32, u32) {
+		let width = text.lines().map(|line| self.line_width(line)).max().unwrap_or(0);
+		(width, text.lines().count() as u32 * self.line_height)
+	}
+
+	/// Call `plot` with the position of every set pixel of `text`, aligned to `x`, `y`
+	pub fn rasterize(
+		&self,
+		text: &str,
+		x: i32,
+		y: i32,
+		align: TextAlign,
+		vertical_align: VerticalAlign,
+		mut plot: impl FnMut(i32, i32),
+	) {
+		for (mut pen_x, line_y, line) in self.layout(text, x, y, align, vertical_align) {
+			for ch in line.chars() {
+				let glyph = match self.glyph(ch) {
+					Some(glyph) => glyph,
+					None => continue,
+				};
+				for gy in 0..glyph.height {
+					for gx in 0..glyph.width {
+						if glyph.is_set(gx, gy) {
+							plot(pen_x + glyph.x_offset + gx as i32, line_y + glyph.y_offset + gy as i32);
+						}
+					}
+				}
+				pen_x += glyph.advance;
+			}
+		}
+	}
+
+	// Top left corner of each line
+	pub(crate) fn layout<'a>(
+		&'a self,
+		text: &'a str,
+		x: i32,
+		y: i32,
+		align: TextAlign,
+		vertical_align: VerticalAlign,
+	) -> impl Iterator<Item = (i32, i32, &'a str)> + 'a {
+		let height = (text.lines().count() as u32 * self.line_height) as i32;
+		let top = match vertical_align {
+			VerticalAlign::Top => y,
+			VerticalAlign::Middle => y - height / 2,
+			VerticalAlign::Bottom => y - height,
+		};
+		text.lines().enumerate().map(move |(i, line)| {
+			let width = self.line_width(line) as i32;
+			let left = match align {
+				TextAlign::Left => x,
+				TextAlign::Center => x - width / 2,
+				TextAlign::Right => x - width,
+			};
+			(left, top + i as i32 * self.line_height as i32, line)
+		})
+	}
+}
+
+impl<'a, T: Blendable> BufferRegion<'a, T> {
+	/// Draw `text` anchored at `x`, `y`, clipped to the region
+	pub fn draw_text(&mut self, x: i32, y: i32, text: &str, font: &BitmapFont, style: &TextStyle<T>) {
+		let mut plot = |x: i32, y: i32, pixel: &T| {
+			if let Some(dst) = self.get_mut(x, y) {
+				*dst = pixel.blend(dst);
+			}
+		};
+
+		if let Some(background) = &style.background {
+			for (left, top, line) in font.layout(text, x, y, style.align, style.vertical_align) {
+				for py in top..top + font.line_height() as i32 {
+					for px in left..left + font.line_width(line) as i32 {
+						plot(px, py, background);
+					}
+				}
+			}
+		}
+		font.rasterize(text, x, y, style.align, style.vertical_align, |x, y| {
+			plot(x, y, &style.color)
+		});
+	}
+}
+
+impl<T: Blendable> Buffer<T> {
+	pub fn draw_text(&mut self, x: i32, y: i32, text: &str, font: &BitmapFont, style: &TextStyle<T>) {
+		self.as_region_mut().draw_text(x, y, text, font, style);
+	}
+}
@@ -0,0 +1,118 @@
+use super::{BitmapFont, FontError, Glyph, MAX_GLYPH_SIZE};
+use std::convert::TryFrom;
+
+/// Parse a font in the Glyph Bitmap Distribution Format
+pub(super) fn parse(source: &str) -> Result<BitmapFont, FontError> {
+	let err = |msg: &str| FontError(format!("BDF: {}", msg));
+	let mut lines = source.lines().map(str::trim);
+	if !matches!(lines.next(), Some(l) if l.starts_with("STARTFONT")) {
+		return Err(err("missing STARTFONT"));
+	}
+
+	let mut bounding_box = None;
+	let mut ascent = None;
+	let mut descent = None;
+	let mut glyphs = vec![];
+
+	while let Some(line) = lines.next() {
+		let mut words = line.split_whitespace();
+		match words.next() {
+			Some("FONTBOUNDINGBOX") => bounding_box = Some(numbers::<4>(words).ok_or_else(|| err(line))?),
+			Some("FONT_ASCENT") => ascent = words.next().and_then(|v| v.parse::<i32>().ok()),
+			Some("FONT_DESCENT") => descent = words.next().and_then(|v| v.parse::<i32>().ok()),
+			Some("STARTCHAR") => {
+				let mut encoding = -1;
+				let mut advance = None;
+				let mut bbx = None;
+				let mut rows = vec![];
+				let mut in_bitmap = false;
+				for line in &mut lines {
+					let mut words = line.split_whitespace();
+					match words.next() {
+						Some("ENDCHAR") => break,
+						Some("ENCODING") => {
+							encoding = words.next().and_then(|v| v.parse().ok()).ok_or_else(|| err(line))?
+						}
+						Some("DWIDTH") => advance = words.next().and_then(|v| v.parse::<i32>().ok()),
+						Some("BBX") => bbx = Some(numbers::<4>(words).ok_or_else(|| err(line))?),
+						Some("BITMAP") => in_bitmap = true,
+						Some(hex) if in_bitmap => rows.push(hex.to_string()),
+						_ => {}
+					}
+				}
+				glyphs.push((encoding, advance, bbx, rows));
+			}
+			Some("ENDFONT") => break,
+			_ => {}
+		}
+	}
+
+	let [_, fbb_height, _, fbb_y] = bounding_box.ok_or_else(|| err("missing FONTBOUNDINGBOX"))?;
+	let ascent = ascent.unwrap_or(fbb_height + fbb_y);
+	let descent = descent.unwrap_or(-fbb_y);
+	let mut font = BitmapFont::new((ascent + descent).max(1) as u32);
+
+	for (encoding, advance, bbx, rows) in glyphs {
+		let ch = match char::from_u32(encoding as u32) {
+			Some(ch) if encoding >= 0 => ch,
+			_ => continue,
+		};
+		let [width, height, x_offset, y_offset] = bbx.ok_or_else(|| err("missing BBX"))?;
+		let (width, height) = match (u32::try_from(width), u32::try_from(height)) {
+			(Ok(w), Ok(h)) if w <= MAX_GLYPH_SIZE && h <= MAX_GLYPH_SIZE => (w, h),
+			_ => return Err(err(&format!("invalid BBX size {}x{}", width, height))),
+		};
+		let len = (width as usize).checked_mul(height as usize).ok_or_else(|| err("BBX too large"))?;
+		let mut bitmap = Vec::with_capacity(len);
+		for y in 0..height as usize {
+			let row = rows.get(y).map(String::as_str).unwrap_or("");
+			for x in 0..width as usize {
+				let nibble = row.get(x / 4..x / 4 + 1).and_then(|n| u8::from_str_radix(n, 16).ok());
+				bitmap.push(matches!(nibble, Some(n) if n & (8 >> (x % 4)) != 0));
+			}
+		}
+
+		let mut glyph = Glyph::new(width, height, bitmap);
+		glyph.x_offset = x_offset;
+		// BDF offsets are from the baseline, with Y pointing up
+		glyph.y_offset = ascent - (height as i32 + y_offset);
+		glyph.advance = advance.unwrap_or(width as i32);
+		font.insert(ch, glyph);
+	}
+
+	Ok(font)
+}
+
+fn numbers<'a, const N: usize>(mut words: impl Iterator<Item = &'a str>) -> Option<[i32; N]> {
+	let mut values = [0; N];
+	for value in values.iter_mut() {
+		*value = words.next()?.parse().ok()?;
+	}
+	Some(values)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn font_with_bbx(bbx: &str) -> Result<BitmapFont, FontError> {
+		let glyph = format!("STARTCHAR A\nENCODING 65\nBBX {}\nBITMAP\n90\n60\nENDCHAR", bbx);
+		parse(&format!("STARTFONT 2.1\nFONTBOUNDINGBOX 4 2 0 0\n{}\nENDFONT\n", glyph))
+	}
+
+	#[test]
+	fn glyph() {
+		let font = font_with_bbx("4 2 0 0").unwrap();
+		let glyph = font.glyph('A').unwrap();
+		let bits: Vec<bool> = (0..8).map(|i| glyph.is_set(i % 4, i / 4)).collect();
+		assert_eq!(bits, vec![true, false, false, true, false, true, true, false]);
+	}
+
+	#[test]
+	fn bbx_size() {
+		assert!(font_with_bbx("65536 65536 0 0").is_err());
+		assert!(font_with_bbx("257 1 0 0").is_err());
+		assert!(font_with_bbx("-1 2 0 0").is_err());
+		assert!(font_with_bbx("256 256 0 0").is_ok());
+	}
+}
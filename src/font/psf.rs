@@ -0,0 +1,166 @@
+use super::{BitmapFont, FontError, Glyph, MAX_GLYPH_SIZE};
+
+pub(super) const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+pub(super) const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02 | 0x04;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_START_SEQUENCE: u16 = 0xfffe;
+
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_START_SEQUENCE: u8 = 0xfe;
+
+/// Parse a PC Screen Font, as used by the Linux console
+pub(super) fn parse(data: &[u8]) -> Result<BitmapFont, FontError> {
+	if data.starts_with(&PSF2_MAGIC) {
+		parse_psf2(data)
+	} else if data.starts_with(&PSF1_MAGIC) {
+		parse_psf1(data)
+	} else {
+		Err(FontError("PSF: unknown magic number".to_string()))
+	}
+}
+
+fn parse_psf1(data: &[u8]) -> Result<BitmapFont, FontError> {
+	let mode = *data.get(2).ok_or_else(truncated)?;
+	let height = *data.get(3).ok_or_else(truncated)? as u32;
+	let count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+	let glyphs = data.get(4..4 + count * height as usize).ok_or_else(truncated)?;
+
+	let mut chars: Vec<Vec<char>> = vec![vec![]; count];
+	if mode & PSF1_MODE_HAS_TABLE != 0 {
+		let table = &data[4 + glyphs.len()..];
+		let mut idx = 0;
+		let mut in_sequence = false;
+		for pair in table.chunks_exact(2) {
+			match u16::from_le_bytes([pair[0], pair[1]]) {
+				PSF1_SEPARATOR => {
+					idx += 1;
+					in_sequence = false;
+				}
+				PSF1_START_SEQUENCE => in_sequence = true,
+				code if !in_sequence && idx < count => chars[idx].extend(char::from_u32(code as u32)),
+				_ => {}
+			}
+		}
+	} else {
+		identity_map(&mut chars);
+	}
+
+	Ok(build(glyphs, &chars, 8, height, height as usize))
+}
+
+fn parse_psf2(data: &[u8]) -> Result<BitmapFont, FontError> {
+	let header = |i: usize| -> Result<u32, FontError> {
+		let bytes = data.get(i * 4..i * 4 + 4).ok_or_else(truncated)?;
+		Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+	};
+	let header_size = header(2)? as usize;
+	let flags = header(3)?;
+	let count = header(4)? as usize;
+	let glyph_size = header(5)? as usize;
+	let height = header(6)?;
+	let width = header(7)?;
+	if width == 0 || height == 0 || glyph_size == 0 {
+		return Err(FontError(format!("PSF: empty glyphs {}x{}, {} bytes", width, height, glyph_size)));
+	}
+	if width > MAX_GLYPH_SIZE || height > MAX_GLYPH_SIZE {
+		return Err(FontError(format!("PSF: glyph size {}x{} too large", width, height)));
+	}
+	if glyph_size < height as usize * (width as usize).div_ceil(8) {
+		return Err(FontError("PSF: glyph size too small".to_string()));
+	}
+	// Checked before `count` sizes any allocations, as the header can claim billions of glyphs
+	if count > data.len().saturating_sub(header_size) / glyph_size {
+		return Err(truncated());
+	}
+	let glyphs = data.get(header_size..header_size + count * glyph_size).ok_or_else(truncated)?;
+
+	let mut chars: Vec<Vec<char>> = vec![vec![]; count];
+	if flags & PSF2_HAS_UNICODE_TABLE != 0 {
+		let table = &data[header_size + glyphs.len()..];
+		for (idx, entry) in table.split(|b| *b == PSF2_SEPARATOR).take(count).enumerate() {
+			// Only single codepoints, sequences of combining characters are ignored
+			let singles = entry.split(|b| *b == PSF2_START_SEQUENCE).next().unwrap_or(&[]);
+			chars[idx].extend(String::from_utf8_lossy(singles).chars());
+		}
+	} else {
+		identity_map(&mut chars);
+	}
+
+	Ok(build(glyphs, &chars, width, height, glyph_size))
+}
+
+fn truncated() -> FontError {
+	FontError("PSF: file is truncated".to_string())
+}
+
+// Without a unicode table, assume glyph N is codepoint N
+fn identity_map(chars: &mut [Vec<char>]) {
+	for (idx, c) in chars.iter_mut().enumerate() {
+		c.extend(char::from_u32(idx as u32));
+	}
+}
+
+fn build(glyphs: &[u8], chars: &[Vec<char>], width: u32, height: u32, glyph_size: usize) -> BitmapFont {
+	let row_size = (width as usize).div_ceil(8);
+	let mut font = BitmapFont::new(height);
+	if glyph_size == 0 {
+		return font;
+	}
+	for (data, chars) in glyphs.chunks_exact(glyph_size).zip(chars) {
+		let mut bitmap = Vec::with_capacity(width as usize * height as usize);
+		for y in 0..height as usize {
+			for x in 0..width as usize {
+				bitmap.push(data[y * row_size + x / 8] & (0x80 >> (x % 8)) != 0);
+			}
+		}
+		let glyph = Glyph::new(width, height, bitmap);
+		for ch in chars {
+			font.insert(*ch, glyph.clone());
+		}
+	}
+	font
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn psf2(count: u32, glyph_size: u32, height: u32, width: u32, glyphs: &[u8]) -> Vec<u8> {
+		let mut data = PSF2_MAGIC.to_vec();
+		for value in [0, 32, 0, count, glyph_size, height, width] {
+			data.extend_from_slice(&u32::to_le_bytes(value));
+		}
+		data.extend_from_slice(glyphs);
+		data
+	}
+
+	#[test]
+	fn psf2_glyphs() {
+		// Two 3x2 glyphs, one byte per row
+		let font = parse(&psf2(2, 2, 2, 3, &[0b1010_0000, 0b0100_0000, 0, 0b1110_0000])).unwrap();
+		let glyph = font.glyph('\u{0}').unwrap();
+		let bits: Vec<bool> = (0..6).map(|i| glyph.is_set(i % 3, i / 3)).collect();
+		assert_eq!(bits, vec![true, false, true, false, true, false]);
+		assert!(font.glyph('\u{1}').unwrap().is_set(2, 1));
+		assert!(font.glyph('\u{2}').is_none());
+	}
+
+	#[test]
+	fn psf2_bad_header() {
+		// Claims 4 billion empty glyphs, which used to try to allocate a character list for each
+		assert!(parse(&psf2(u32::MAX, 0, 0, 0, &[])).is_err());
+		assert!(parse(&psf2(u32::MAX, 0, 8, 8, &[])).is_err());
+		assert!(parse(&psf2(u32::MAX, 1, 1, 1, &[0; 64])).is_err());
+		assert!(parse(&psf2(3, 2, 2, 3, &[0; 4])).is_err());
+		assert!(parse(&psf2(1, 2, 2, 0, &[0; 2])).is_err());
+		assert!(parse(&PSF2_MAGIC).is_err());
+
+		let mut past_end = psf2(0, 2, 2, 3, &[]);
+		past_end[8] = 200;
+		assert!(parse(&past_end).is_err());
+	}
+}
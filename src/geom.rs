@@ -24,6 +24,33 @@ impl Rect {
 	pub fn is_infinite_height(&self) -> bool {
 		self.height == INFINITY
 	}
+
+	pub fn contains(&self, x: i32, y: i32) -> bool {
+		x >= self.x && y >= self.y && (x as i64) < self.right() && (y as i64) < self.bottom()
+	}
+
+	/// Area covered by both rects, `None` if they don't overlap
+	pub fn intersection(&self, other: &Rect) -> Option<Rect> {
+		let x = self.x.max(other.x);
+		let y = self.y.max(other.y);
+		let right = self.right().min(other.right());
+		let bottom = self.bottom().min(other.bottom());
+		if right <= x as i64 || bottom <= y as i64 {
+			return None;
+		}
+		let width = (right - x as i64).min(INFINITY as i64) as i32;
+		let height = (bottom - y as i64).min(INFINITY as i64) as i32;
+		Some(Rect::new(x, y, width, height))
+	}
+
+	// Widened so infinite rects don't overflow
+	fn right(&self) -> i64 {
+		self.x as i64 + self.width as i64
+	}
+
+	fn bottom(&self) -> i64 {
+		self.y as i64 + self.height as i64
+	}
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
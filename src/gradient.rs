@@ -1,30 +1,397 @@
-use crate::Blendable;
+use crate::{Blendable, Color, Hsv};
+
+/// Colour space used to interpolate between `Color` stops
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GradientSpace {
+	/// Gamma encoded sRGB, cheap but midpoints look dark and muddy
+	#[default]
+	Rgb,
+	/// Linear light RGB
+	Linear,
+	/// Perceptually even steps in lightness and hue
+	Oklab,
+	/// Hue, saturation and value, taking the shortest way around the hue circle
+	Hsv,
+}
+
+/// How the position between two stops is shaped before interpolating
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Easing {
+	#[default]
+	Linear,
+	/// Smoothstep, easing in and out of every stop
+	Smooth,
+	EaseIn,
+	EaseOut,
+	/// Hard edges, each stop's colour is held until the next stop
+	Step,
+	/// Split each segment into this many flat bands
+	Steps(u32),
+}
+
+impl Easing {
+	pub fn apply(&self, t: f32) -> f32 {
+		let t = t.clamp(0.0, 1.0);
+		match *self {
+			Easing::Linear => t,
+			Easing::Smooth => t * t * (3.0 - 2.0 * t),
+			Easing::EaseIn => t * t,
+			Easing::EaseOut => t * (2.0 - t),
+			Easing::Step => {
+				if t < 1.0 {
+					0.0
+				} else {
+					1.0
+				}
+			}
+			Easing::Steps(n) => {
+				let n = n.max(1) as f32;
+				(t * n).floor().min(n - 1.0) / (n - 1.0).max(1.0)
+			}
+		}
+	}
+}
+
+/// What happens to positions outside `0.0..=1.0`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Spread {
+	/// Use the colour of the first or last stop
+	#[default]
+	Pad,
+	/// Start again from the first stop
+	Repeat,
+	/// Go back and forth between the first and last stop
+	Mirror,
+}
+
+impl Spread {
+	pub fn apply(&self, n: f32) -> f32 {
+		if !n.is_finite() {
+			return 0.0;
+		}
+		match self {
+			Spread::Pad => n.clamp(0.0, 1.0),
+			Spread::Repeat => n.rem_euclid(1.0),
+			Spread::Mirror => {
+				let n = n.rem_euclid(2.0);
+				if n > 1.0 {
+					2.0 - n
+				} else {
+					n
+				}
+			}
+		}
+	}
+}
 
 #[derive(Clone)]
 pub struct Gradient<C: Blendable> {
 	colors: Vec<C>,
+	positions: Vec<f32>,
+	easing: Easing,
+	spread: Spread,
+	mix: fn(&C, &C, f32) -> C,
 }
 
 impl<C: Blendable> Gradient<C> {
+	/// Evenly spaced colours from 0.0 to 1.0
 	pub fn new(colors: Vec<C>) -> Self {
-		Self { colors }
+		let last = colors.len().saturating_sub(1).max(1) as f32;
+		let stops = colors.into_iter().enumerate().map(|(i, c)| (i as f32 / last, c)).collect();
+		Self::with_stops(stops)
+	}
+
+	/// Colours at explicit positions, usually in the range `0.0..=1.0`
+	pub fn with_stops(mut stops: Vec<(f32, C)>) -> Self {
+		stops.retain(|(pos, _)| pos.is_finite());
+		stops.sort_by(|l, r| l.0.partial_cmp(&r.0).unwrap());
+		let (positions, colors) = stops.into_iter().unzip();
+		Self {
+			colors,
+			positions,
+			easing: Easing::Linear,
+			spread: Spread::Pad,
+			mix: |l, r, t| l.lerp(r, t),
+		}
+	}
+
+	pub fn with_easing(mut self, easing: Easing) -> Self {
+		self.easing = easing;
+		self
+	}
+
+	pub fn with_spread(mut self, spread: Spread) -> Self {
+		self.spread = spread;
+		self
+	}
+
+	/// Use a custom function to interpolate between two stops
+	pub fn with_mix(mut self, mix: fn(&C, &C, f32) -> C) -> Self {
+		self.mix = mix;
+		self
+	}
+
+	pub fn add_stop(&mut self, position: f32, color: C) {
+		if !position.is_finite() {
+			return;
+		}
+		let idx = self.positions.iter().take_while(|p| **p <= position).count();
+		self.positions.insert(idx, position);
+		self.colors.insert(idx, color);
 	}
 
 	pub fn colors(&self) -> &[C] {
 		&self.colors
 	}
 
+	pub fn positions(&self) -> &[f32] {
+		&self.positions
+	}
+
+	pub fn easing(&self) -> Easing {
+		self.easing
+	}
+
+	pub fn spread(&self) -> Spread {
+		self.spread
+	}
+
+	/// Colour at position `n`. An empty gradient is always the default colour.
 	pub fn color(&self, n: f32) -> C {
-		if n < 0.0 {
-			self.colors[0].clone()
-		} else if n > 1.0 {
-			self.colors[self.colors.len() - 1].clone()
+		let len = self.colors.len();
+		if len == 0 {
+			return C::default();
+		}
+
+		let n = self.spread.apply(n);
+		match self.segment(n) {
+			Ok(idx) => self.sample(idx, n),
+			Err(idx) => self.colors[idx],
+		}
+	}
+
+	// Index of the stop ending the segment containing `n`, or the index of the stop to use
+	// as-is when `n` is outside every segment
+	fn segment(&self, n: f32) -> Result<usize, usize> {
+		let len = self.colors.len();
+		let idx = self.positions.iter().take_while(|p| **p <= n).count();
+		if idx == 0 {
+			Err(0)
+		} else if idx == len {
+			Err(len - 1)
 		} else {
-			let idx = (self.colors.len() as f32 - 1.0) * n;
-			let top = self.colors[idx.floor() as usize];
-			let bot = self.colors[idx.ceil() as usize];
+			Ok(idx)
+		}
+	}
+
+	fn sample(&self, idx: usize, n: f32) -> C {
+		let (start, end) = (self.positions[idx - 1], self.positions[idx]);
+		let t = if end > start { (n - start) / (end - start) } else { 1.0 };
+		(self.mix)(&self.colors[idx - 1], &self.colors[idx], self.easing.apply(t))
+	}
+}
+
+impl Gradient<Color> {
+	pub fn with_space(self, space: GradientSpace) -> Self {
+		let mix: fn(&Color, &Color, f32) -> Color = match space {
+			GradientSpace::Rgb => |l, r, t| l.lerp(r, t),
+			GradientSpace::Linear => |l, r, t| l.lerp_linear(r, t),
+			GradientSpace::Oklab => |l, r, t| l.lerp_oklab(r, t),
+			GradientSpace::Hsv => lerp_hsv,
+		};
+		self.with_mix(mix)
+	}
+
+	/// Colour at position `n`, nudged by up to one colour level using a dither `threshold` in the
+	/// range `0.0..1.0`. Varying the threshold per pixel hides the banding of wide, subtle gradients.
+	pub fn color_dithered(&self, n: f32, threshold: f32) -> Color {
+		if self.colors.is_empty() {
+			return Color::default();
+		}
+		let n = self.spread.apply(n);
+		let idx = match self.segment(n) {
+			Ok(idx) => idx,
+			Err(idx) => return self.colors[idx],
+		};
 
-			top.lerp(&bot, idx.fract())
+		// Distance along the gradient between neighbouring colour levels
+		let (from, to) = (self.colors[idx - 1], self.colors[idx]);
+		let levels = (from.r as i32 - to.r as i32)
+			.abs()
+			.max((from.g as i32 - to.g as i32).abs())
+			.max((from.b as i32 - to.b as i32).abs())
+			.max((from.a as i32 - to.a as i32).abs());
+		if levels == 0 {
+			return from;
 		}
+		let (start, end) = (self.positions[idx - 1], self.positions[idx]);
+		let step = (end - start) / levels as f32;
+		let n = (n + threshold * step).clamp(start, end);
+		self.sample(idx, n)
+	}
+
+	/// Perceptually uniform blue, green and yellow colormap
+	pub fn viridis() -> Self {
+		Self::from_polynomial(&VIRIDIS)
+	}
+
+	/// Perceptually uniform black, purple, orange and white colormap
+	pub fn magma() -> Self {
+		Self::from_polynomial(&MAGMA)
+	}
+
+	/// Google's rainbow colormap, with smoother lightness than the classic jet
+	pub fn turbo() -> Self {
+		Self::from_polynomial(&TURBO)
+	}
+
+	// Sample a colormap approximated by polynomials on each channel
+	fn from_polynomial(coefficients: &[[f32; 3]]) -> Self {
+		let stops = (0..=COLORMAP_STOPS)
+			.map(|i| {
+				let t = i as f32 / COLORMAP_STOPS as f32;
+				let mut rgb = [0.0; 3];
+				for c in coefficients.iter().rev() {
+					for ch in 0..3 {
+						rgb[ch] = rgb[ch] * t + c[ch];
+					}
+				}
+				let [r, g, b] = rgb.map(|v| (v.clamp(0.0, 1.0) * 255.0).round() as u8);
+				(t, Color::rgb(r, g, b))
+			})
+			.collect();
+		Self::with_stops(stops)
+	}
+}
+
+fn lerp_hsv(l: &Color, r: &Color, t: f32) -> Color {
+	let (from, to) = (l.to_hsv(), r.to_hsv());
+	// Hue of a grey is meaningless, so borrow the other end's
+	let from_h = if from.s == 0.0 { to.h } else { from.h };
+	let to_h = if to.s == 0.0 { from_h } else { to.h };
+	let mut dh = to_h - from_h;
+	if dh > 180.0 {
+		dh -= 360.0;
+	} else if dh < -180.0 {
+		dh += 360.0;
+	}
+	let lerp = |a: f32, b: f32| a + (b - a) * t;
+	Hsv {
+		h: (from_h + dh * t).rem_euclid(360.0),
+		s: lerp(from.s, to.s),
+		v: lerp(from.v, to.v),
+		a: lerp(from.a, to.a),
+	}
+	.into()
+}
+
+const COLORMAP_STOPS: usize = 32;
+
+// Polynomial fits of the colormaps, lowest order first. Viridis and magma by Matt Zucker,
+// turbo by Anton Mikhailov.
+const VIRIDIS: [[f32; 3]; 7] = [
+	[0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+	[0.105_093_04, 1.404_613_5, 1.384_590_1],
+	[-0.330_861_83, 0.214_847_56, 0.095_095_16],
+	[-4.634_230_5, -5.799_101, -19.332_441],
+	[6.228_27, 14.179_933, 56.690_55],
+	[4.776_385, -13.745_145, -65.353_03],
+	[-5.435_456, 4.645_852_6, 26.312_435],
+];
+
+const MAGMA: [[f32; 3]; 7] = [
+	[-0.002_136_485, -0.000_749_655_05, -0.005_386_128],
+	[0.251_660_54, 0.677_523_2, 2.494_026_6],
+	[8.353_717, -3.577_719_5, 0.314_467_9],
+	[-27.668_733, 14.264_731, -13.649_213],
+	[52.176_14, -27.943_607, 12.944_169],
+	[-50.768_524, 29.046_583, 4.234_153],
+	[18.655_705, -11.489_774, -5.601_961_5],
+];
+
+const TURBO: [[f32; 3]; 6] = [
+	[0.135_721_38, 0.091_402_61, 0.106_673_3],
+	[4.615_392_6, 2.194_188_4, 12.641_946],
+	[-42.660_323, 4.842_966_6, -60.582_05],
+	[132.131_08, -14.185_033, 110.362_77],
+	[-152.942_4, 4.277_299, -89.903_11],
+	[59.286_38, 2.829_566, 27.348_25],
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ramp(spread: Spread) -> Gradient<f32> {
+		Gradient::new(vec![0.0, 1.0]).with_spread(spread)
+	}
+
+	#[test]
+	fn stop_positions() {
+		assert_eq!(Gradient::new(vec![0.0f32, 0.5, 1.0]).positions(), [0.0, 0.5, 1.0]);
+
+		let mut gradient = Gradient::with_stops(vec![(1.0, 10.0f32), (f32::NAN, 5.0), (0.0, 0.0), (0.25, 2.0)]);
+		assert_eq!(gradient.positions(), [0.0, 0.25, 1.0]);
+		assert_eq!(gradient.colors(), [0.0, 2.0, 10.0]);
+		assert_eq!(gradient.color(0.25), 2.0);
+		assert_eq!(gradient.color(0.125), 1.0);
+		assert_eq!(gradient.color(0.625), 6.0);
+
+		gradient.add_stop(0.5, 4.0);
+		gradient.add_stop(f32::INFINITY, 100.0);
+		assert_eq!(gradient.positions(), [0.0, 0.25, 0.5, 1.0]);
+		assert_eq!(gradient.color(0.75), 7.0);
+
+		assert_eq!(Gradient::<f32>::new(vec![]).color(0.5), 0.0);
+		assert_eq!(Gradient::new(vec![3.0f32]).color(0.5), 3.0);
+	}
+
+	#[test]
+	fn spread_outside_range() {
+		let pad = ramp(Spread::Pad);
+		assert_eq!(pad.color(-0.5), 0.0);
+		assert_eq!(pad.color(1.5), 1.0);
+
+		let repeat = ramp(Spread::Repeat);
+		assert_eq!(repeat.color(1.25), 0.25);
+		assert_eq!(repeat.color(-0.25), 0.75);
+
+		let mirror = ramp(Spread::Mirror);
+		assert_eq!(mirror.color(1.25), 0.75);
+		assert_eq!(mirror.color(-0.25), 0.25);
+		assert_eq!(mirror.color(2.25), 0.25);
+
+		for spread in [Spread::Pad, Spread::Repeat, Spread::Mirror] {
+			assert_eq!(ramp(spread).color(f32::NAN), 0.0);
+		}
+	}
+
+	#[test]
+	fn easing() {
+		let steps = ramp(Spread::Pad).with_easing(Easing::Steps(3));
+		assert_eq!([0.1, 0.4, 0.9, 1.0].map(|n| steps.color(n)), [0.0, 0.5, 1.0, 1.0]);
+		let step = ramp(Spread::Pad).with_easing(Easing::Step);
+		assert_eq!([0.0, 0.99, 1.0].map(|n| step.color(n)), [0.0, 0.0, 1.0]);
+		assert_eq!(ramp(Spread::Pad).with_easing(Easing::Smooth).color(0.5), 0.5);
+	}
+
+	#[test]
+	fn hsv_takes_short_way_round() {
+		let (red, green, blue, magenta) = (
+			Color::rgb(255, 0, 0),
+			Color::rgb(0, 255, 0),
+			Color::rgb(0, 0, 255),
+			Color::rgb(255, 0, 255),
+		);
+		let mid = |from, to| Gradient::new(vec![from, to]).with_space(GradientSpace::Hsv).color(0.5);
+		// 0 to 300 degrees goes back through 330, not forwards through green at 150
+		assert_eq!(mid(red, magenta), Color::rgb(255, 0, 128));
+		assert_eq!(mid(magenta, red), Color::rgb(255, 0, 128));
+		// 240 to 0 goes forwards through 300
+		assert_eq!(mid(blue, red), magenta);
+		assert_eq!(mid(red, green), Color::rgb(255, 255, 0));
+		// A grey end keeps the other end's hue
+		assert_eq!(mid(Color::black(), blue), Color::rgb(64, 64, 128));
+		assert_eq!(mid(blue, Color::white()).to_hsv().h, 240.0);
 	}
 }
@@ -0,0 +1,171 @@
+use crate::{Blendable, Buffer, Canvas, Color, GifWriter};
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+/// Something that can be animated and drawn to a canvas
+pub trait Scene<O: Blendable> {
+	/// Advance the scene by `dt` seconds
+	fn update(&mut self, dt: f32);
+	fn draw(&mut self, canvas: &mut Canvas<O>);
+}
+
+/// Renders a `Scene` without a terminal, stepping time by a fixed amount every frame.
+///
+/// Nothing depends on the wall clock, so rendering the same scene twice gives identical frames.
+pub struct HeadlessRenderer<O: Blendable> {
+	canvas: Canvas<O>,
+	timestep: Duration,
+	frame: u64,
+}
+
+impl<O: Blendable> HeadlessRenderer<O> {
+	pub fn new(width: u32, height: u32, timestep: Duration) -> Self {
+		Self {
+			canvas: Canvas::new(width, height),
+			timestep,
+			frame: 0,
+		}
+	}
+
+	/// Step time by `1 / fps` seconds every frame
+	pub fn with_fps(width: u32, height: u32, fps: u32) -> Self {
+		Self::new(width, height, Duration::from_secs(1) / fps.max(1))
+	}
+
+	pub fn canvas(&self) -> &Canvas<O> {
+		&self.canvas
+	}
+
+	pub fn canvas_mut(&mut self) -> &mut Canvas<O> {
+		&mut self.canvas
+	}
+
+	pub fn timestep(&self) -> Duration {
+		self.timestep
+	}
+
+	/// Number of frames rendered so far
+	pub fn frame(&self) -> u64 {
+		self.frame
+	}
+
+	/// Scene time of the next frame
+	pub fn time(&self) -> Duration {
+		self.timestep.mul_f64(self.frame as f64)
+	}
+
+	/// Render a single frame. The first frame is drawn before any time has passed.
+	pub fn step(&mut self, scene: &mut impl Scene<O>) -> &Buffer<O> {
+		if self.frame > 0 {
+			scene.update(self.timestep.as_secs_f32());
+		}
+		self.canvas.fill(O::default());
+		scene.draw(&mut self.canvas);
+		self.frame += 1;
+		self.canvas.buffer()
+	}
+
+	/// Render `frames` frames, passing each one to `callback` as it's finished
+	pub fn render_with<E>(
+		&mut self,
+		scene: &mut impl Scene<O>,
+		frames: u64,
+		mut callback: impl FnMut(u64, &Buffer<O>) -> Result<(), E>,
+	) -> Result<(), E> {
+		for _ in 0..frames {
+			let frame = self.frame;
+			let buffer = self.step(scene);
+			callback(frame, buffer)?;
+		}
+		Ok(())
+	}
+
+	/// Render `frames` frames into memory
+	pub fn render(&mut self, scene: &mut impl Scene<O>, frames: u64) -> Vec<Buffer<O>> {
+		let mut output = Vec::with_capacity(frames as usize);
+		let _: Result<(), ()> = self.render_with(scene, frames, |_, buffer| {
+			output.push(buffer.clone());
+			Ok(())
+		});
+		output
+	}
+}
+
+impl HeadlessRenderer<Color> {
+	/// Render `frames` frames into `dir` as numbered PNG images, e.g. `frame-0000.png`
+	pub fn render_to_dir(
+		&mut self,
+		scene: &mut impl Scene<Color>,
+		frames: u64,
+		dir: impl AsRef<Path>,
+	) -> Result<(), Box<dyn Error>> {
+		let dir = dir.as_ref();
+		std::fs::create_dir_all(dir)?;
+		let start = self.frame;
+		self.render_with(scene, frames, |frame, buffer| {
+			buffer.save(dir.join(format!("frame-{:04}.png", frame - start)))
+		})
+	}
+
+	/// Render `frames` frames into an animated GIF, played back at the renderer's timestep
+	pub fn render_to_gif(
+		&mut self,
+		scene: &mut impl Scene<Color>,
+		frames: u64,
+		path: impl AsRef<Path>,
+	) -> Result<(), Box<dyn Error>> {
+		let mut gif = GifWriter::create(path, self.timestep)?;
+		self.render_with(scene, frames, |_, buffer| gif.push(buffer))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// A dot bouncing around the canvas, fading in as it goes
+	struct Bounce {
+		time: f32,
+	}
+
+	impl Scene<Color> for Bounce {
+		fn update(&mut self, dt: f32) {
+			self.time += dt;
+		}
+
+		fn draw(&mut self, canvas: &mut Canvas<Color>) {
+			let (w, h) = (canvas.width() as f32, canvas.height() as f32);
+			let x = (self.time * 7.3).sin() * 0.5 + 0.5;
+			let y = (self.time * 4.1).cos() * 0.5 + 0.5;
+			let shade = (self.time * 40.0) as u8;
+			if let Some(p) = canvas.buffer_mut().get_mut((x * (w - 1.0)) as i32, (y * (h - 1.0)) as i32) {
+				*p = Color::rgb(shade, 255 - shade, 128);
+			}
+		}
+	}
+
+	#[test]
+	fn deterministic() {
+		let render = || HeadlessRenderer::with_fps(16, 12, 30).render(&mut Bounce { time: 0.0 }, 60);
+		let (first, second) = (render(), render());
+		assert_eq!(first.len(), 60);
+		for (l, r) in first.iter().zip(&second) {
+			assert_eq!(l.as_bytes(), r.as_bytes());
+		}
+		// The scene actually moved
+		assert_ne!(first[0].as_bytes(), first[59].as_bytes());
+	}
+
+	#[test]
+	fn time() {
+		let mut renderer: HeadlessRenderer<Color> = HeadlessRenderer::with_fps(4, 4, 60);
+		assert_eq!(renderer.time(), Duration::ZERO);
+		renderer.render(&mut Bounce { time: 0.0 }, 3);
+		assert_eq!(renderer.time(), Duration::from_secs(1) / 60 * 3);
+
+		// More frames than fit in a u32
+		renderer.frame = 1 << 33;
+		assert_eq!(renderer.time().as_millis(), renderer.timestep().as_nanos() * (1 << 33) / 1_000_000);
+	}
+}
@@ -2,19 +2,38 @@ pub mod buffer;
 mod camera;
 mod canvas;
 mod color;
+mod dither;
+mod export;
+mod font;
 mod geom;
 mod gradient;
+mod headless;
 mod material;
 mod mesh;
+mod palette;
+mod presenter;
 mod shader;
 mod texture;
 
-pub use buffer::{Blendable, Buffer};
+pub use buffer::{
+	BlendMode, Blendable, Blit, Buffer, BufferRegion, BufferView, ConnectedRegion, Connectivity, FillRule, Filterable,
+	GradientShape, Paint, Path, ResampleFilter,
+};
 pub use camera::*;
-pub use canvas::{Canvas, DrawContext};
-pub use color::Color;
+pub use canvas::{AsciiEdges, AsciiRamp, Canvas, Cell, CellMode, DrawContext, DEFAULT_ASCII_RAMP};
+pub use color::{
+	linear_to_srgb, srgb_to_linear, Color, HdrColor, Hsl, Hsv, LinearColor, Oklab, Oklch, ParseColorError,
+	PremultipliedColor, ToneMap, ToneMapper, XTERM_PALETTE,
+};
+pub use dither::*;
+pub use export::*;
+pub use font::*;
+pub use geom::Rect;
 pub use gradient::*;
+pub use headless::*;
 pub use material::Material;
 pub use mesh::{Cube, Mesh, StaticMesh, Triangle};
+pub use palette::Palette;
+pub use presenter::*;
 pub use shader::*;
 pub use texture::*;
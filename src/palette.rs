@@ -0,0 +1,146 @@
+use crate::{Buffer, Color};
+
+// Most pixels sampled when building a palette, larger images are subsampled
+const MAX_SAMPLES: usize = 1 << 16;
+
+/// A limited set of colours, e.g. for Sixel or GIF output
+#[derive(Clone, Debug, Default)]
+pub struct Palette {
+	colors: Vec<Color>,
+	// Nearest palette index for each 15-bit RGB colour, filled in on demand
+	lookup: Vec<u16>,
+}
+
+impl Palette {
+	pub fn new(colors: Vec<Color>) -> Self {
+		Self {
+			colors,
+			lookup: vec![],
+		}
+	}
+
+	/// Build a palette of at most `max_colors` using the median cut algorithm.
+	/// Pixels with less than 50% alpha are ignored.
+	pub fn median_cut(buffer: &Buffer<Color>, max_colors: usize) -> Self {
+		let pixels = buffer.as_slice();
+		let sample = |step: usize| -> Vec<[u8; 3]> {
+			pixels
+				.iter()
+				.step_by(step)
+				.filter(|p| p.a >= 128)
+				.map(|p| [p.r, p.g, p.b])
+				.collect()
+		};
+		let mut samples = sample((pixels.len() / MAX_SAMPLES).max(1));
+		// Subsampling can miss every opaque pixel of a mostly transparent image
+		if samples.is_empty() {
+			samples = sample(1);
+		}
+		if samples.is_empty() || max_colors == 0 {
+			return Self::new(vec![]);
+		}
+
+		let mut boxes = vec![samples];
+		while boxes.len() < max_colors {
+			// Split the box with the widest range on any channel
+			let mut widest = None;
+			for (i, b) in boxes.iter().enumerate() {
+				if b.len() < 2 {
+					continue;
+				}
+				let (channel, range) = widest_channel(b);
+				if range > 0 && !matches!(widest, Some((_, _, r)) if r >= range) {
+					widest = Some((i, channel, range));
+				}
+			}
+			let (i, channel, _) = match widest {
+				Some(widest) => widest,
+				None => break,
+			};
+
+			let mut b = boxes.swap_remove(i);
+			b.sort_unstable_by_key(|p| p[channel]);
+
+			// Split near the median, but never between two pixels of the same value
+			let mid = b[b.len() / 2][channel];
+			let lower = b.iter().position(|p| p[channel] == mid).unwrap();
+			let upper = b.iter().rposition(|p| p[channel] == mid).unwrap() + 1;
+			let split = if lower == 0 || (upper < b.len() && upper - b.len() / 2 < b.len() / 2 - lower) {
+				upper
+			} else {
+				lower
+			};
+			let upper = b.split_off(split);
+			boxes.push(b);
+			boxes.push(upper);
+		}
+
+		let colors = boxes
+			.iter()
+			.map(|b| {
+				let mut sum = [0u64; 3];
+				for p in b {
+					for c in 0..3 {
+						sum[c] += p[c] as u64;
+					}
+				}
+				let n = b.len() as u64;
+				Color::rgb((sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8)
+			})
+			.collect();
+
+		Self::new(colors)
+	}
+
+	pub fn colors(&self) -> &[Color] {
+		&self.colors
+	}
+
+	pub fn len(&self) -> usize {
+		self.colors.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.colors.is_empty()
+	}
+
+	/// Index of the nearest palette colour
+	pub fn nearest(&mut self, color: &Color) -> usize {
+		if self.lookup.is_empty() {
+			self.lookup = vec![u16::MAX; 1 << 15];
+		}
+		let key = ((color.r as usize >> 3) << 10) | ((color.g as usize >> 3) << 5) | (color.b as usize >> 3);
+		if self.lookup[key] == u16::MAX {
+			self.lookup[key] = self.search(color) as u16;
+		}
+		self.lookup[key] as usize
+	}
+
+	fn search(&self, color: &Color) -> usize {
+		let mut best = 0;
+		let mut best_dist = i32::MAX;
+		for (i, c) in self.colors.iter().enumerate() {
+			let dr = c.r as i32 - color.r as i32;
+			let dg = c.g as i32 - color.g as i32;
+			let db = c.b as i32 - color.b as i32;
+			let dist = dr * dr + dg * dg + db * db;
+			if dist < best_dist {
+				best = i;
+				best_dist = dist;
+			}
+		}
+		best
+	}
+}
+
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+	let mut min = [255u8; 3];
+	let mut max = [0u8; 3];
+	for p in pixels {
+		for c in 0..3 {
+			min[c] = min[c].min(p[c]);
+			max[c] = max[c].max(p[c]);
+		}
+	}
+	(0..3).map(|c| (c, max[c] - min[c])).max_by_key(|(_, range)| *range).unwrap()
+}
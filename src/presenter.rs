@@ -0,0 +1,786 @@
+mod asciicast;
+pub use asciicast::*;
+mod kitty;
+pub use kitty::*;
+mod sixel;
+pub use sixel::*;
+
+use crate::{AsciiRamp, Blendable, Buffer, Canvas, Cell, CellMode, Color, Dither, Ditherer};
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Environment variable that overrides the detected colour depth, e.g. `TORU_COLOR_DEPTH=256`
+pub const COLOR_DEPTH_ENV: &str = "TORU_COLOR_DEPTH";
+
+/// How many colours the terminal can display
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ColorDepth {
+	/// 24-bit RGB
+	#[default]
+	TrueColor,
+	/// xterm 256 colour palette
+	Ansi256,
+	/// The 16 base ANSI colours
+	Ansi16,
+	/// No colour at all, only symbols and reverse video
+	Mono,
+}
+
+impl ColorDepth {
+	/// Detect the colour depth of the current terminal. `TORU_COLOR_DEPTH` takes
+	/// priority, then `NO_COLOR`, `COLORTERM` and `TERM`.
+	pub fn detect() -> Self {
+		if let Ok(depth) = env::var(COLOR_DEPTH_ENV) {
+			if let Ok(depth) = depth.parse() {
+				return depth;
+			}
+			log::warn!("Invalid {}: {:?}", COLOR_DEPTH_ENV, depth);
+		}
+		if env::var_os("NO_COLOR").is_some() {
+			return Self::Mono;
+		}
+
+		Self::from_env(env::var("COLORTERM").ok().as_deref(), env::var("TERM").ok().as_deref())
+	}
+
+	/// Nearest colour that can be displayed at this depth
+	pub fn quantize(&self, color: &Color) -> Color {
+		let mut quantized = match self {
+			Self::TrueColor => return *color,
+			Self::Ansi256 => Color::from_8bit_ansi(color.as_8bit_ansi()),
+			Self::Ansi16 => Color::from_8bit_ansi(color.as_4bit_ansi()),
+			Self::Mono => {
+				if color.luminance() > 0.5 {
+					Color::white()
+				} else {
+					Color::black()
+				}
+			}
+		};
+		quantized.a = color.a;
+		quantized
+	}
+
+	/// Guess the colour depth from the values of `COLORTERM` and `TERM`
+	pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+		if let Some("truecolor") | Some("24bit") = colorterm {
+			return Self::TrueColor;
+		}
+
+		match term {
+			None | Some("") | Some("dumb") => Self::Mono,
+			Some(term) if term.ends_with("-direct") => Self::TrueColor,
+			Some(term) if term.contains("256color") => Self::Ansi256,
+			Some(term) if term.starts_with("vt") && !term.contains("color") => Self::Mono,
+			Some(_) => Self::Ansi16,
+		}
+	}
+}
+
+#[derive(Debug)]
+pub struct ParseColorDepthError(String);
+
+impl fmt::Display for ParseColorDepthError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "unknown color depth: {:?}", self.0)
+	}
+}
+
+impl Error for ParseColorDepthError {}
+
+impl FromStr for ColorDepth {
+	type Err = ParseColorDepthError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.trim().to_lowercase().as_str() {
+			"truecolor" | "24bit" | "24" | "rgb" => Ok(Self::TrueColor),
+			"256" | "8bit" | "8" => Ok(Self::Ansi256),
+			"16" | "4bit" | "4" | "ansi" => Ok(Self::Ansi16),
+			"mono" | "none" | "1" | "0" => Ok(Self::Mono),
+			_ => Err(ParseColorDepthError(s.to_string())),
+		}
+	}
+}
+
+/// Query for the primary device attributes, answered with `ESC [ ? ... c`
+pub const QUERY_DEVICE_ATTRIBUTES: &str = "\x1b[c";
+/// Query for the size of a cell in pixels, answered with `ESC [ 6 ; height ; width t`
+pub const QUERY_CELL_SIZE: &str = "\x1b[16t";
+
+/// Cell size assumed when the terminal hasn't reported one
+pub const DEFAULT_CELL_SIZE: (u32, u32) = (10, 20);
+
+/// Features supported by the terminal, usually filled in from the responses to
+/// `QUERY_DEVICE_ATTRIBUTES`, `QUERY_KITTY_GRAPHICS` and `QUERY_CELL_SIZE`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+	pub sixel: bool,
+	pub kitty_graphics: bool,
+	/// Width and height of a cell in pixels
+	pub cell_size: Option<(u32, u32)>,
+}
+
+impl Capabilities {
+	/// Update from a primary device attributes response, e.g. `ESC [ ? 62 ; 4 ; 22 c`
+	pub fn parse_device_attributes(&mut self, response: &[u8]) -> bool {
+		match csi_params(response, b'?', b'c') {
+			Some(params) => {
+				self.sixel = params.contains(&4);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Update from the response to `QUERY_KITTY_GRAPHICS`, e.g. `ESC _ G i=31;OK ESC \`
+	pub fn parse_kitty_graphics(&mut self, response: &[u8]) -> bool {
+		let start = match response.windows(3).position(|w| w == b"\x1b_G") {
+			Some(start) => start + 3,
+			None => return false,
+		};
+		let body = &response[start..];
+		let end = body.windows(2).position(|w| w == b"\x1b\\").unwrap_or(body.len());
+		self.kitty_graphics = body[..end].ends_with(b";OK");
+		true
+	}
+
+	/// Update from a cell size report, e.g. `ESC [ 6 ; 20 ; 10 t`
+	pub fn parse_cell_size(&mut self, response: &[u8]) -> bool {
+		match csi_params(response, 0, b't').as_deref() {
+			Some([6, height, width]) if *width > 0 && *height > 0 => {
+				self.cell_size = Some((*width, *height));
+				true
+			}
+			_ => false,
+		}
+	}
+
+	pub fn cell_size_or_default(&self) -> (u32, u32) {
+		self.cell_size.unwrap_or(DEFAULT_CELL_SIZE)
+	}
+}
+
+// Find a `ESC [ <prefix> params <terminator>` sequence and return its numeric parameters
+fn csi_params(response: &[u8], prefix: u8, terminator: u8) -> Option<Vec<u32>> {
+	let start = response.windows(2).position(|w| w == b"\x1b[")? + 2;
+	let mut body = &response[start..];
+	if prefix != 0 {
+		body = body.strip_prefix(&[prefix])?;
+	}
+	let end = body.iter().position(|b| *b == terminator)?;
+	std::str::from_utf8(&body[..end])
+		.ok()?
+		.split(';')
+		.map(|p| p.parse().ok())
+		.collect()
+}
+
+/// How the canvas is turned into terminal output
+#[derive(Clone, Debug)]
+pub enum PresentMode {
+	Cells(CellMode),
+	/// Characters from a density ramp. With `ColorDepth::Mono` this is plain text, one line per
+	/// row without any escape sequences, so it can be written to logs and serial consoles.
+	Ascii(AsciiRamp),
+	/// Sixel graphics at the terminal's pixel resolution, when supported
+	Sixel(SixelEncoder),
+	/// Kitty graphics protocol at the terminal's pixel resolution, when supported
+	Kitty(KittyEncoder),
+}
+
+impl Default for PresentMode {
+	fn default() -> Self {
+		Self::Cells(CellMode::default())
+	}
+}
+
+impl From<CellMode> for PresentMode {
+	fn from(mode: CellMode) -> Self {
+		Self::Cells(mode)
+	}
+}
+
+impl From<AsciiRamp> for PresentMode {
+	fn from(ramp: AsciiRamp) -> Self {
+		Self::Ascii(ramp)
+	}
+}
+
+impl From<SixelEncoder> for PresentMode {
+	fn from(encoder: SixelEncoder) -> Self {
+		Self::Sixel(encoder)
+	}
+}
+
+impl From<KittyEncoder> for PresentMode {
+	fn from(encoder: KittyEncoder) -> Self {
+		Self::Kitty(encoder)
+	}
+}
+
+/// Statistics about the most recently presented frame
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+	/// Bytes written to the terminal
+	pub bytes: usize,
+	/// Total number of cells in the frame, zero for graphics protocols
+	pub cells: usize,
+	/// Cells that were redrawn
+	pub changed: usize,
+}
+
+/// Writes a `Canvas` to a terminal as ANSI escape sequences.
+///
+/// The previous frame is kept, so only cells that have changed are redrawn.
+#[derive(Clone, Debug)]
+pub struct Presenter {
+	mode: PresentMode,
+	color_depth: ColorDepth,
+	ditherer: Ditherer,
+	capabilities: Capabilities,
+	fallback: CellMode,
+	previous: Vec<Vec<Cell<Color>>>,
+	stats: FrameStats,
+}
+
+impl Presenter {
+	/// Create a presenter using the colour depth detected from the environment
+	pub fn new(mode: impl Into<PresentMode>) -> Self {
+		Self::with_color_depth(mode, ColorDepth::detect())
+	}
+
+	pub fn with_color_depth(mode: impl Into<PresentMode>, color_depth: ColorDepth) -> Self {
+		Self {
+			mode: mode.into(),
+			color_depth,
+			ditherer: Ditherer::default(),
+			capabilities: Capabilities::default(),
+			fallback: CellMode::HalfBlock,
+			previous: vec![],
+			stats: FrameStats::default(),
+		}
+	}
+
+	pub fn mode(&self) -> &PresentMode {
+		&self.mode
+	}
+
+	pub fn set_mode(&mut self, mode: impl Into<PresentMode>) {
+		self.mode = mode.into();
+		self.invalidate();
+	}
+
+	pub fn color_depth(&self) -> ColorDepth {
+		self.color_depth
+	}
+
+	pub fn set_color_depth(&mut self, color_depth: ColorDepth) {
+		self.color_depth = color_depth;
+		self.invalidate();
+	}
+
+	/// Forget the previous frame, so the next one is drawn in full. Use this
+	/// when something else has drawn over the terminal.
+	pub fn invalidate(&mut self) {
+		self.previous.clear();
+	}
+
+	/// Statistics about the last presented frame
+	pub fn stats(&self) -> FrameStats {
+		self.stats
+	}
+
+	pub fn dither(&self) -> Dither {
+		self.ditherer.method()
+	}
+
+	/// Dithering used when the colour depth is lower than truecolor
+	pub fn set_dither(&mut self, dither: Dither) {
+		if dither != self.ditherer.method() {
+			self.ditherer = Ditherer::new(dither);
+		}
+	}
+
+	pub fn capabilities(&self) -> Capabilities {
+		self.capabilities
+	}
+
+	pub fn set_capabilities(&mut self, capabilities: Capabilities) {
+		self.capabilities = capabilities;
+		self.invalidate();
+	}
+
+	pub fn fallback(&self) -> CellMode {
+		self.fallback
+	}
+
+	/// Cell mode used when the terminal doesn't support the graphics protocol of the present mode
+	pub fn set_fallback(&mut self, fallback: CellMode) {
+		self.fallback = fallback;
+		self.invalidate();
+	}
+
+	/// Size of the canvas needed to fill a terminal of `columns` x `rows` cells
+	pub fn canvas_size(&self, columns: u32, rows: u32) -> (u32, u32) {
+		match &self.mode {
+			PresentMode::Sixel(_) if self.capabilities.sixel => {
+				// Leave the last row empty, so the image doesn't scroll the terminal
+				let (w, h) = self.capabilities.cell_size_or_default();
+				(columns * w, rows.saturating_sub(1) * h)
+			}
+			PresentMode::Kitty(_) if self.capabilities.kitty_graphics => {
+				let (w, h) = self.capabilities.cell_size_or_default();
+				(columns * w, rows * h)
+			}
+			PresentMode::Ascii(_) => (columns, rows),
+			_ => self.cell_mode().canvas_size(columns, rows),
+		}
+	}
+
+	// Cell mode used for everything except ASCII and supported graphics protocols
+	fn cell_mode(&self) -> CellMode {
+		match &self.mode {
+			PresentMode::Cells(mode) => *mode,
+			_ => self.fallback,
+		}
+	}
+
+	/// Write a whole frame, starting from the top left of the terminal
+	pub fn present<O>(&mut self, canvas: &Canvas<O>, out: &mut impl Write) -> io::Result<()>
+	where
+		O: Blendable + Into<Color>,
+	{
+		match &self.mode {
+			PresentMode::Sixel(encoder) if self.capabilities.sixel => {
+				let mut buf = b"\x1b[H".to_vec();
+				encoder.encode(&to_colors(canvas.buffer()), &mut buf)?;
+				return self.write_graphics(&buf, out);
+			}
+			PresentMode::Kitty(encoder) if self.capabilities.kitty_graphics => {
+				let mut buf = b"\x1b[H".to_vec();
+				encoder.encode(&to_colors(canvas.buffer()), &mut buf)?;
+				return self.write_graphics(&buf, out);
+			}
+			_ => {}
+		}
+
+		let mut rows = self.cells(canvas.buffer(), canvas.depth());
+		if let (PresentMode::Ascii(_), ColorDepth::Mono) = (&self.mode, self.color_depth) {
+			return self.write_text(&rows, out);
+		}
+		if self.color_depth != ColorDepth::TrueColor && self.ditherer.method() != Dither::None {
+			rows = self.dither_cells(rows);
+		}
+
+		let rows: Vec<Vec<Cell<Color>>> = rows
+			.iter()
+			.map(|row| row.iter().map(|cell| self.resolve(cell)).collect())
+			.collect();
+
+		// Redraw everything if the size has changed
+		let full = self.previous.len() != rows.len()
+			|| self.previous.iter().zip(rows.iter()).any(|(l, r)| l.len() != r.len());
+
+		let mut stats = FrameStats::default();
+		let mut writer = FrameWriter::new(self.color_depth);
+		for (y, row) in rows.iter().enumerate() {
+			stats.cells += row.len();
+			for (x, cell) in row.iter().enumerate() {
+				if !full && self.previous[y][x] == *cell {
+					continue;
+				}
+				stats.changed += 1;
+				writer.move_to(x, y, row)?;
+				writer.write_cell(cell)?;
+			}
+		}
+		let buf = writer.finish();
+		stats.bytes = buf.len();
+
+		self.previous = rows;
+		self.stats = stats;
+		out.write_all(&buf)?;
+		out.flush()
+	}
+
+	// Write a frame drawn with a graphics protocol, which always replaces the whole screen
+	fn write_graphics(&mut self, buf: &[u8], out: &mut impl Write) -> io::Result<()> {
+		self.previous.clear();
+		self.stats = FrameStats {
+			bytes: buf.len(),
+			..FrameStats::default()
+		};
+		out.write_all(buf)?;
+		out.flush()
+	}
+
+	// Write a frame as plain text, which is always written in full after the previous one
+	fn write_text(&mut self, rows: &[Vec<Cell<Color>>], out: &mut impl Write) -> io::Result<()> {
+		let mut buf = String::new();
+		for row in rows {
+			buf.extend(row.iter().map(|cell| cell.symbol));
+			buf.push('\n');
+		}
+		let cells = rows.iter().map(Vec::len).sum();
+		self.previous.clear();
+		self.stats = FrameStats {
+			bytes: buf.len(),
+			cells,
+			changed: cells,
+		};
+		out.write_all(buf.as_bytes())?;
+		out.flush()
+	}
+
+	fn cells<O>(&self, buffer: &Buffer<O>, depth: &Buffer<f32>) -> Vec<Vec<Cell<Color>>>
+	where
+		O: Blendable + Into<Color>,
+	{
+		let into_color = |cell: Cell<O>| Cell::new(cell.symbol, cell.fg.into(), cell.bg.into());
+		match &self.mode {
+			PresentMode::Ascii(ramp) => (0..buffer.height())
+				.map(|y| {
+					(0..buffer.width())
+						.filter_map(|x| ramp.cell(buffer, Some(depth), x, y).map(into_color))
+						.collect()
+				})
+				.collect(),
+			_ => {
+				let mode = self.cell_mode();
+				let (columns, rows) = mode.cell_count(buffer);
+				(0..rows)
+					.map(|y| (0..columns).filter_map(|x| mode.cell(buffer, x, y).map(into_color)).collect())
+					.collect()
+			}
+		}
+	}
+
+	// Dither the colours of each cell, so every cell is one sample of the threshold map. Dithering
+	// the pixels before they're merged into cells would average most of the pattern away.
+	fn dither_cells(&self, rows: Vec<Vec<Cell<Color>>>) -> Vec<Vec<Cell<Color>>> {
+		let width = rows.iter().map(Vec::len).max().unwrap_or(0) as u32;
+		let mut fg = Buffer::new(width, rows.len() as u32);
+		let mut bg = Buffer::new(width, rows.len() as u32);
+		for (y, row) in rows.iter().enumerate() {
+			for (x, cell) in row.iter().enumerate() {
+				if let Some(f) = fg.get_mut(x as i32, y as i32) {
+					*f = cell.fg;
+				}
+				if let Some(b) = bg.get_mut(x as i32, y as i32) {
+					*b = cell.bg;
+				}
+			}
+		}
+		let fg = self.ditherer.apply(&fg, self.color_depth);
+		let bg = self.ditherer.apply(&bg, self.color_depth);
+
+		rows.into_iter()
+			.enumerate()
+			.map(|(y, row)| {
+				row.into_iter()
+					.enumerate()
+					.map(|(x, cell)| {
+						let (x, y) = (x as i32, y as i32);
+						let fg = fg.get(x, y).copied().unwrap_or(cell.fg);
+						let bg = bg.get(x, y).copied().unwrap_or(cell.bg);
+						Cell::new(cell.symbol, fg, bg)
+					})
+					.collect()
+			})
+			.collect()
+	}
+
+	// Quantize a cell to exactly what will be displayed, so unchanged output can be skipped
+	fn resolve(&self, cell: &Cell<Color>) -> Cell<Color> {
+		let (symbol, mut fg, mut bg) = match self.color_depth {
+			ColorDepth::Mono => {
+				// Reverse video stands in for a bright background
+				let fg_on = cell.fg.luminance() > cell.bg.luminance();
+				let bg_on = cell.bg.luminance() > 0.5;
+				match (fg_on, bg_on) {
+					(true, false) => (cell.symbol, Color::white(), Color::black()),
+					(false, true) => (cell.symbol, Color::black(), Color::white()),
+					(true, true) => (' ', Color::black(), Color::white()),
+					(false, false) => (' ', Color::white(), Color::black()),
+				}
+			}
+			depth => (cell.symbol, depth.quantize(&cell.fg), depth.quantize(&cell.bg)),
+		};
+		fg.a = 255;
+		bg.a = 255;
+
+		// The foreground of an empty cell is never seen
+		if symbol == ' ' && self.color_depth != ColorDepth::Mono {
+			fg = bg;
+		}
+
+		Cell::new(symbol, fg, bg)
+	}
+}
+
+// Longest run of unchanged cells that will be rewritten instead of moving the cursor over them
+const MAX_REWRITE: usize = 4;
+
+// Builds the escape sequences for a frame, tracking the cursor and current
+// colours to avoid redundant sequences
+struct FrameWriter {
+	depth: ColorDepth,
+	buf: Vec<u8>,
+	cursor: Option<(usize, usize)>,
+	fg: Option<Color>,
+	bg: Option<Color>,
+}
+
+impl FrameWriter {
+	fn new(depth: ColorDepth) -> Self {
+		Self {
+			depth,
+			buf: Vec::with_capacity(4096),
+			cursor: None,
+			fg: None,
+			bg: None,
+		}
+	}
+
+	// Can this cell be written without changing colours
+	fn matches_sgr(&self, cell: &Cell<Color>) -> bool {
+		self.bg == Some(cell.bg) && (self.fg == Some(cell.fg) || (cell.symbol == ' ' && self.depth != ColorDepth::Mono))
+	}
+
+	fn move_to(&mut self, x: usize, y: usize, row: &[Cell<Color>]) -> io::Result<()> {
+		match self.cursor {
+			Some((cx, cy)) if cx == x && cy == y => return Ok(()),
+			Some((cx, cy)) if cy == y && x > cx => {
+				let gap = x - cx;
+				let skipped = &row[cx..x];
+				let rewrite_len: usize = skipped.iter().map(|c| c.symbol.len_utf8()).sum();
+				let move_len = if gap == 1 { 3 } else { 3 + digits(gap) };
+				if gap <= MAX_REWRITE && rewrite_len < move_len && skipped.iter().all(|c| self.matches_sgr(c)) {
+					for cell in skipped {
+						self.write_cell(cell)?;
+					}
+				} else if gap == 1 {
+					self.buf.extend_from_slice(b"\x1b[C");
+				} else {
+					write!(self.buf, "\x1b[{}C", gap)?;
+				}
+			}
+			Some((_, cy)) if cy + 1 == y && x == 0 => self.buf.extend_from_slice(b"\r\n"),
+			_ if x == 0 => write!(self.buf, "\x1b[{}H", y + 1)?,
+			_ => write!(self.buf, "\x1b[{};{}H", y + 1, x + 1)?,
+		}
+		self.cursor = Some((x, y));
+		Ok(())
+	}
+
+	fn write_cell(&mut self, cell: &Cell<Color>) -> io::Result<()> {
+		if self.depth == ColorDepth::Mono {
+			let reverse = cell.bg == Color::white();
+			if self.bg != Some(cell.bg) {
+				self.buf.extend_from_slice(if reverse { b"\x1b[7m" } else { b"\x1b[27m" });
+				self.fg = Some(cell.fg);
+				self.bg = Some(cell.bg);
+			}
+		} else if !self.matches_sgr(cell) {
+			self.buf.extend_from_slice(b"\x1b[");
+			let set_fg = cell.symbol != ' ' && self.fg != Some(cell.fg);
+			if set_fg {
+				write_sgr_color(&mut self.buf, self.depth, &cell.fg, false)?;
+				self.fg = Some(cell.fg);
+			}
+			if self.bg != Some(cell.bg) {
+				if set_fg {
+					self.buf.push(b';');
+				}
+				write_sgr_color(&mut self.buf, self.depth, &cell.bg, true)?;
+				self.bg = Some(cell.bg);
+			}
+			self.buf.push(b'm');
+		}
+
+		let mut symbol = [0; 4];
+		self.buf.extend_from_slice(cell.symbol.encode_utf8(&mut symbol).as_bytes());
+		if let Some((x, _)) = &mut self.cursor {
+			*x += 1;
+		}
+		Ok(())
+	}
+
+	fn finish(mut self) -> Vec<u8> {
+		if !self.buf.is_empty() {
+			self.buf.extend_from_slice(b"\x1b[0m");
+		}
+		self.buf
+	}
+}
+
+fn to_colors<O>(buffer: &Buffer<O>) -> Buffer<Color>
+where
+	O: Blendable + Into<Color>,
+{
+	let (w, h) = buffer.size();
+	let mut colors = Buffer::new(w, h);
+	for y in 0..h as i32 {
+		for x in 0..w as i32 {
+			if let (Some(dst), Some(src)) = (colors.get_mut(x, y), buffer.get(x, y)) {
+				*dst = (*src).into();
+			}
+		}
+	}
+	colors
+}
+
+fn digits(n: usize) -> usize {
+	n.to_string().len()
+}
+
+/// Write the SGR parameters that select a foreground or background colour
+pub(crate) fn write_sgr_color(
+	out: &mut impl Write,
+	depth: ColorDepth,
+	color: &Color,
+	background: bool,
+) -> io::Result<()> {
+	match depth {
+		ColorDepth::TrueColor => {
+			let code = if background { 48 } else { 38 };
+			write!(out, "{};2;{};{};{}", code, color.r, color.g, color.b)
+		}
+		ColorDepth::Ansi256 => {
+			let code = if background { 48 } else { 38 };
+			write!(out, "{};5;{}", code, color.as_8bit_ansi())
+		}
+		ColorDepth::Ansi16 => {
+			let idx = color.as_4bit_ansi();
+			let base = match (background, idx < 8) {
+				(false, true) => 30,
+				(false, false) => 90 - 8,
+				(true, true) => 40,
+				(true, false) => 100 - 8,
+			};
+			write!(out, "{}", base + idx)
+		}
+		ColorDepth::Mono => Ok(()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn sgr(depth: ColorDepth, color: Color, background: bool) -> String {
+		let mut out = vec![];
+		write_sgr_color(&mut out, depth, &color, background).unwrap();
+		String::from_utf8(out).unwrap()
+	}
+
+	// Present a frame, returning the bytes written
+	fn present(presenter: &mut Presenter, canvas: &Canvas<Color>) -> String {
+		let mut out = vec![];
+		presenter.present(canvas, &mut out).unwrap();
+		assert_eq!(presenter.stats().bytes, out.len());
+		String::from_utf8(out).unwrap()
+	}
+
+	fn set(canvas: &mut Canvas<Color>, x: i32, y: i32, color: Color) {
+		*canvas.buffer_mut().get_mut(x, y).unwrap() = color;
+	}
+
+	#[test]
+	fn depth_from_env() {
+		assert_eq!(ColorDepth::from_env(Some("truecolor"), Some("xterm")), ColorDepth::TrueColor);
+		assert_eq!(ColorDepth::from_env(Some("24bit"), None), ColorDepth::TrueColor);
+		assert_eq!(ColorDepth::from_env(None, Some("xterm-direct")), ColorDepth::TrueColor);
+		assert_eq!(ColorDepth::from_env(None, Some("xterm-256color")), ColorDepth::Ansi256);
+		assert_eq!(ColorDepth::from_env(Some("yes"), Some("screen-256color")), ColorDepth::Ansi256);
+		assert_eq!(ColorDepth::from_env(None, Some("xterm")), ColorDepth::Ansi16);
+		assert_eq!(ColorDepth::from_env(None, Some("vt100")), ColorDepth::Mono);
+		assert_eq!(ColorDepth::from_env(None, Some("dumb")), ColorDepth::Mono);
+		assert_eq!(ColorDepth::from_env(None, Some("")), ColorDepth::Mono);
+		assert_eq!(ColorDepth::from_env(None, None), ColorDepth::Mono);
+	}
+
+	#[test]
+	fn depth_from_str() {
+		assert_eq!("truecolor".parse::<ColorDepth>().unwrap(), ColorDepth::TrueColor);
+		assert_eq!(" 24bit ".parse::<ColorDepth>().unwrap(), ColorDepth::TrueColor);
+		assert_eq!("256".parse::<ColorDepth>().unwrap(), ColorDepth::Ansi256);
+		assert_eq!("ANSI".parse::<ColorDepth>().unwrap(), ColorDepth::Ansi16);
+		assert_eq!("none".parse::<ColorDepth>().unwrap(), ColorDepth::Mono);
+		assert!("65536".parse::<ColorDepth>().is_err());
+		assert!("".parse::<ColorDepth>().is_err());
+	}
+
+	#[test]
+	fn sgr_per_depth() {
+		let orange = Color::rgb(255, 135, 0);
+		assert_eq!(sgr(ColorDepth::TrueColor, orange, false), "38;2;255;135;0");
+		assert_eq!(sgr(ColorDepth::TrueColor, orange, true), "48;2;255;135;0");
+		assert_eq!(sgr(ColorDepth::Ansi256, orange, false), "38;5;208");
+		assert_eq!(sgr(ColorDepth::Ansi256, orange, true), "48;5;208");
+		assert_eq!(sgr(ColorDepth::Ansi256, Color::grey(128), false), "38;5;244");
+
+		// Normal colours use 30-37 and 40-47, bright ones 90-97 and 100-107
+		assert_eq!(sgr(ColorDepth::Ansi16, Color::rgb(205, 0, 0), false), "31");
+		assert_eq!(sgr(ColorDepth::Ansi16, Color::rgb(205, 0, 0), true), "41");
+		assert_eq!(sgr(ColorDepth::Ansi16, Color::rgb(255, 0, 0), false), "91");
+		assert_eq!(sgr(ColorDepth::Ansi16, Color::rgb(255, 0, 0), true), "101");
+		assert_eq!(sgr(ColorDepth::Mono, orange, false), "");
+	}
+
+	#[test]
+	fn frame_writer_diffs() {
+		let (black, red, green) = (Color::black(), Color::rgb(255, 0, 0), Color::rgb(0, 255, 0));
+		let mut presenter = Presenter::with_color_depth(CellMode::Full, ColorDepth::TrueColor);
+		let mut canvas = Canvas::new(8, 2);
+		canvas.buffer_mut().fill(black);
+		set(&mut canvas, 2, 0, red);
+
+		// The first frame is drawn in full
+		let first = present(&mut presenter, &canvas);
+		assert_eq!(
+			first,
+			"\x1b[1H\x1b[48;2;0;0;0m  \x1b[48;2;255;0;0m \x1b[48;2;0;0;0m     \r\n        \x1b[0m"
+		);
+		assert_eq!(presenter.stats().changed, 16);
+
+		// A one cell gap in the current colour is rewritten, longer gaps and new rows are moved over
+		set(&mut canvas, 1, 0, red);
+		set(&mut canvas, 3, 0, red);
+		set(&mut canvas, 7, 0, green);
+		set(&mut canvas, 6, 1, green);
+		let second = present(&mut presenter, &canvas);
+		assert_eq!(second, "\x1b[1;2H\x1b[48;2;255;0;0m   \x1b[3C\x1b[48;2;0;255;0m \x1b[2;7H \x1b[0m");
+		assert_eq!(presenter.stats(), FrameStats { bytes: second.len(), cells: 16, changed: 4 });
+
+		// A gap in another colour is skipped, and the next row starts with a newline
+		set(&mut canvas, 7, 0, black);
+		set(&mut canvas, 0, 1, red);
+		set(&mut canvas, 2, 1, red);
+		assert_eq!(
+			present(&mut presenter, &canvas),
+			"\x1b[1;8H\x1b[48;2;0;0;0m \r\n\x1b[48;2;255;0;0m \x1b[C \x1b[0m"
+		);
+
+		// Nothing is written for an unchanged frame
+		assert_eq!(present(&mut presenter, &canvas), "");
+		assert_eq!(presenter.stats(), FrameStats { bytes: 0, cells: 16, changed: 0 });
+	}
+
+	#[test]
+	fn ascii_mono_is_plain_text() {
+		let mut presenter = Presenter::with_color_depth(AsciiRamp::new(" .#"), ColorDepth::Mono);
+		let mut canvas = Canvas::new(3, 2);
+		canvas.buffer_mut().fill(Color::black());
+		set(&mut canvas, 1, 0, Color::grey(128));
+		set(&mut canvas, 2, 1, Color::white());
+
+		assert_eq!(present(&mut presenter, &canvas), " . \n  #\n");
+		// Every frame is written in full
+		assert_eq!(present(&mut presenter, &canvas), " . \n  #\n");
+		assert_eq!(presenter.stats(), FrameStats { bytes: 8, cells: 6, changed: 6 });
+	}
+}
@@ -0,0 +1,502 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, Write};
+use std::str;
+use std::{thread, time};
+
+pub const ASCIICAST_VERSION: u32 = 2;
+
+/// Header line of an asciicast v2 file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AsciicastHeader {
+	pub width: u32,
+	pub height: u32,
+	/// Unix timestamp of the start of the recording
+	pub timestamp: Option<u64>,
+	pub title: Option<String>,
+	pub env: BTreeMap<String, String>,
+}
+
+impl AsciicastHeader {
+	pub fn new(width: u32, height: u32) -> Self {
+		Self {
+			width,
+			height,
+			..Self::default()
+		}
+	}
+
+	fn to_json(&self) -> String {
+		let mut json = format!(
+			"{{\"version\": {}, \"width\": {}, \"height\": {}",
+			ASCIICAST_VERSION, self.width, self.height
+		);
+		if let Some(timestamp) = self.timestamp {
+			json += &format!(", \"timestamp\": {}", timestamp);
+		}
+		if let Some(title) = &self.title {
+			json += &format!(", \"title\": {}", json_string(title));
+		}
+		if !self.env.is_empty() {
+			let env: Vec<String> = self
+				.env
+				.iter()
+				.map(|(k, v)| format!("{}: {}", json_string(k), json_string(v)))
+				.collect();
+			json += &format!(", \"env\": {{{}}}", env.join(", "));
+		}
+		json + "}"
+	}
+}
+
+/// A single timed event, usually terminal output
+#[derive(Clone, Debug, PartialEq)]
+pub struct AsciicastEvent {
+	/// Seconds since the start of the recording
+	pub time: f64,
+	/// `'o'` for output, `'i'` for input, `'m'` for markers
+	pub kind: char,
+	pub data: String,
+}
+
+/// Captures everything written to it as asciicast v2 output events.
+///
+/// Bytes are buffered until `flush`, which the `Presenter` calls once per
+/// frame, so each frame becomes a single event. Timestamps come from the wall
+/// clock unless set manually with `set_time` or `advance`, which makes headless
+/// recordings deterministic.
+pub struct Recorder<W: Write> {
+	out: W,
+	pending: Vec<u8>,
+	started_at: time::Instant,
+	time: Option<f64>,
+}
+
+impl<W: Write> Recorder<W> {
+	/// Start a recording of a `width` x `height` cell terminal, writing the header immediately
+	pub fn new(out: W, width: u32, height: u32) -> io::Result<Self> {
+		Self::with_header(out, &AsciicastHeader::new(width, height))
+	}
+
+	pub fn with_header(mut out: W, header: &AsciicastHeader) -> io::Result<Self> {
+		writeln!(out, "{}", header.to_json())?;
+		Ok(Self {
+			out,
+			pending: vec![],
+			started_at: time::Instant::now(),
+			time: None,
+		})
+	}
+
+	/// Seconds since the start of the recording
+	pub fn time(&self) -> f64 {
+		self.time.unwrap_or_else(|| self.started_at.elapsed().as_secs_f64())
+	}
+
+	/// Use a manual clock, set to `time` seconds
+	pub fn set_time(&mut self, time: f64) {
+		self.time = Some(time);
+	}
+
+	/// Move the manual clock forward by `dt` seconds
+	pub fn advance(&mut self, dt: f64) {
+		self.time = Some(self.time() + dt);
+	}
+
+	/// Record a marker, e.g. to label a frame in the player
+	pub fn marker(&mut self, label: &str) -> io::Result<()> {
+		self.write_event('m', label)
+	}
+
+	/// Flush any pending output and return the inner writer
+	pub fn finish(mut self) -> io::Result<W> {
+		self.flush()?;
+		Ok(self.out)
+	}
+
+	fn write_event(&mut self, kind: char, data: &str) -> io::Result<()> {
+		writeln!(self.out, "[{:.6}, \"{}\", {}]", self.time(), kind, json_string(data))
+	}
+}
+
+impl<W: Write> Write for Recorder<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.pending.extend_from_slice(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		if !self.pending.is_empty() {
+			// Keep a trailing partial UTF-8 character for the next event
+			let len = match str::from_utf8(&self.pending) {
+				Ok(_) => self.pending.len(),
+				Err(e) if e.error_len().is_none() => e.valid_up_to(),
+				Err(_) => self.pending.len(),
+			};
+			let data = String::from_utf8_lossy(&self.pending[..len]).into_owned();
+			self.pending.drain(..len);
+			if !data.is_empty() {
+				self.write_event('o', &data)?;
+			}
+		}
+		self.out.flush()
+	}
+}
+
+/// A parsed asciicast v2 recording
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Asciicast {
+	pub header: AsciicastHeader,
+	pub events: Vec<AsciicastEvent>,
+}
+
+impl Asciicast {
+	pub fn read(reader: impl BufRead) -> io::Result<Self> {
+		let mut lines = reader.lines();
+		let header = match lines.next() {
+			Some(line) => parse_header(&line?)?,
+			None => return Err(invalid_data("missing asciicast header")),
+		};
+
+		let mut events = vec![];
+		for line in lines {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			events.push(parse_event(&line)?);
+		}
+
+		Ok(Self { header, events })
+	}
+
+	/// All output events concatenated, i.e. the final state of the terminal stream
+	pub fn output(&self) -> String {
+		self.events.iter().filter(|e| e.kind == 'o').map(|e| e.data.as_str()).collect()
+	}
+
+	/// Write the output events to `out`, sleeping between them. A `speed` of
+	/// 2.0 plays twice as fast, and 0.0 or less writes everything without waiting.
+	pub fn play(&self, out: &mut impl Write, speed: f64) -> io::Result<()> {
+		let started_at = time::Instant::now();
+		for event in self.events.iter().filter(|e| e.kind == 'o') {
+			if speed > 0.0 {
+				let at = time::Duration::from_secs_f64(event.time.max(0.0) / speed);
+				let elapsed = started_at.elapsed();
+				if at > elapsed {
+					thread::sleep(at - elapsed);
+				}
+			}
+			out.write_all(event.data.as_bytes())?;
+			out.flush()?;
+		}
+		Ok(())
+	}
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn parse_header(line: &str) -> io::Result<AsciicastHeader> {
+	let mut fields = match json::parse(line)? {
+		json::Value::Object(fields) => fields,
+		_ => return Err(invalid_data("asciicast header isn't an object")),
+	};
+	let mut number = |key: &str| match fields.remove(key) {
+		Some(json::Value::Number(n)) => Some(n),
+		_ => None,
+	};
+	if number("version") != Some(ASCIICAST_VERSION as f64) {
+		return Err(invalid_data("unsupported asciicast version"));
+	}
+	let width = number("width").ok_or_else(|| invalid_data("missing width"))? as u32;
+	let height = number("height").ok_or_else(|| invalid_data("missing height"))? as u32;
+	let timestamp = number("timestamp").map(|t| t as u64);
+	let title = match fields.remove("title") {
+		Some(json::Value::String(title)) => Some(title),
+		_ => None,
+	};
+	let env = match fields.remove("env") {
+		Some(json::Value::Object(env)) => env
+			.into_iter()
+			.filter_map(|(k, v)| match v {
+				json::Value::String(v) => Some((k, v)),
+				_ => None,
+			})
+			.collect(),
+		_ => BTreeMap::new(),
+	};
+
+	Ok(AsciicastHeader {
+		width,
+		height,
+		timestamp,
+		title,
+		env,
+	})
+}
+
+fn parse_event(line: &str) -> io::Result<AsciicastEvent> {
+	match json::parse(line)? {
+		json::Value::Array(values) => match values.as_slice() {
+			[json::Value::Number(time), json::Value::String(kind), json::Value::String(data)] => Ok(AsciicastEvent {
+				time: *time,
+				kind: kind.chars().next().unwrap_or('o'),
+				data: data.clone(),
+			}),
+			_ => Err(invalid_data("malformed asciicast event")),
+		},
+		_ => Err(invalid_data("asciicast event isn't an array")),
+	}
+}
+
+fn json_string(s: &str) -> String {
+	let mut json = String::with_capacity(s.len() + 2);
+	json.push('"');
+	for c in s.chars() {
+		match c {
+			'"' => json.push_str("\\\""),
+			'\\' => json.push_str("\\\\"),
+			'\n' => json.push_str("\\n"),
+			'\r' => json.push_str("\\r"),
+			'\t' => json.push_str("\\t"),
+			c if (c as u32) < 0x20 || c as u32 == 0x7f => json.push_str(&format!("\\u{:04x}", c as u32)),
+			c => json.push(c),
+		}
+	}
+	json.push('"');
+	json
+}
+
+// Just enough JSON to read asciicast files
+mod json {
+	use super::invalid_data;
+	use std::collections::BTreeMap;
+	use std::io;
+	use std::iter::Peekable;
+	use std::str::Chars;
+
+	#[derive(Debug)]
+	pub enum Value {
+		/// `true`, `false` or `null`
+		Literal,
+		Number(f64),
+		String(String),
+		Array(Vec<Value>),
+		Object(BTreeMap<String, Value>),
+	}
+
+	pub fn parse(s: &str) -> io::Result<Value> {
+		let mut chars = s.chars().peekable();
+		let value = parse_value(&mut chars)?;
+		skip_whitespace(&mut chars);
+		if chars.next().is_some() {
+			return Err(invalid_data("trailing characters after JSON value"));
+		}
+		Ok(value)
+	}
+
+	fn skip_whitespace(chars: &mut Peekable<Chars>) {
+		while let Some(c) = chars.peek() {
+			if !c.is_whitespace() {
+				break;
+			}
+			chars.next();
+		}
+	}
+
+	fn expect(chars: &mut Peekable<Chars>, expected: char) -> io::Result<()> {
+		skip_whitespace(chars);
+		if chars.next() == Some(expected) {
+			Ok(())
+		} else {
+			Err(invalid_data("unexpected character in JSON"))
+		}
+	}
+
+	fn parse_value(chars: &mut Peekable<Chars>) -> io::Result<Value> {
+		skip_whitespace(chars);
+		match chars.peek().copied() {
+			Some('{') => {
+				chars.next();
+				let mut fields = BTreeMap::new();
+				skip_whitespace(chars);
+				if chars.peek() == Some(&'}') {
+					chars.next();
+					return Ok(Value::Object(fields));
+				}
+				loop {
+					skip_whitespace(chars);
+					let key = parse_string(chars)?;
+					expect(chars, ':')?;
+					fields.insert(key, parse_value(chars)?);
+					skip_whitespace(chars);
+					match chars.next() {
+						Some(',') => continue,
+						Some('}') => return Ok(Value::Object(fields)),
+						_ => return Err(invalid_data("unterminated JSON object")),
+					}
+				}
+			}
+			Some('[') => {
+				chars.next();
+				let mut values = vec![];
+				skip_whitespace(chars);
+				if chars.peek() == Some(&']') {
+					chars.next();
+					return Ok(Value::Array(values));
+				}
+				loop {
+					values.push(parse_value(chars)?);
+					skip_whitespace(chars);
+					match chars.next() {
+						Some(',') => continue,
+						Some(']') => return Ok(Value::Array(values)),
+						_ => return Err(invalid_data("unterminated JSON array")),
+					}
+				}
+			}
+			Some('"') => Ok(Value::String(parse_string(chars)?)),
+			Some('t') => parse_literal(chars, "true"),
+			Some('f') => parse_literal(chars, "false"),
+			Some('n') => parse_literal(chars, "null"),
+			Some(_) => {
+				let mut number = String::new();
+				while let Some(c) = chars.peek() {
+					if !(c.is_ascii_digit() || "+-.eE".contains(*c)) {
+						break;
+					}
+					number.push(*c);
+					chars.next();
+				}
+				number
+					.parse()
+					.map(Value::Number)
+					.map_err(|_| invalid_data("invalid JSON number"))
+			}
+			None => Err(invalid_data("unexpected end of JSON")),
+		}
+	}
+
+	fn parse_literal(chars: &mut Peekable<Chars>, literal: &str) -> io::Result<Value> {
+		for expected in literal.chars() {
+			if chars.next() != Some(expected) {
+				return Err(invalid_data("invalid JSON literal"));
+			}
+		}
+		Ok(Value::Literal)
+	}
+
+	fn parse_string(chars: &mut Peekable<Chars>) -> io::Result<String> {
+		if chars.next() != Some('"') {
+			return Err(invalid_data("expected JSON string"));
+		}
+		let mut s = String::new();
+		loop {
+			match chars.next() {
+				Some('"') => return Ok(s),
+				Some('\\') => match chars.next() {
+					Some('n') => s.push('\n'),
+					Some('r') => s.push('\r'),
+					Some('t') => s.push('\t'),
+					Some('b') => s.push('\u{8}'),
+					Some('f') => s.push('\u{c}'),
+					Some('u') => {
+						let mut code = parse_hex(chars)?;
+						// Surrogate pair
+						if (0xd800..0xdc00).contains(&code) {
+							if chars.next() != Some('\\') || chars.next() != Some('u') {
+								return Err(invalid_data("unpaired JSON surrogate"));
+							}
+							let low = parse_hex(chars)?;
+							code = 0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff);
+						}
+						s.push(std::char::from_u32(code).unwrap_or('\u{fffd}'));
+					}
+					Some(c) => s.push(c),
+					None => return Err(invalid_data("unterminated JSON string")),
+				},
+				Some(c) => s.push(c),
+				None => return Err(invalid_data("unterminated JSON string")),
+			}
+		}
+	}
+
+	fn parse_hex(chars: &mut Peekable<Chars>) -> io::Result<u32> {
+		let hex: String = chars.by_ref().take(4).collect();
+		u32::from_str_radix(&hex, 16).map_err(|_| invalid_data("invalid JSON unicode escape"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn record_and_replay() {
+		let mut header = AsciicastHeader::new(80, 24);
+		header.timestamp = Some(1_600_000_000);
+		header.title = Some(String::from("a \"quoted\" title"));
+		header.env.insert(String::from("TERM"), String::from("xterm-256color"));
+
+		let mut recorder = Recorder::with_header(vec![], &header).unwrap();
+		recorder.set_time(0.0);
+		recorder.write_all(b"\x1b[2J\x1b[1;1Hhello\r\n").unwrap();
+		recorder.flush().unwrap();
+		recorder.advance(0.5);
+		recorder.marker("frame 2").unwrap();
+		// A multi-byte character split between frames stays in one piece
+		let snowman = "\u{2603}".as_bytes();
+		recorder.write_all(&snowman[..1]).unwrap();
+		recorder.flush().unwrap();
+		recorder.advance(0.25);
+		recorder.write_all(&snowman[1..]).unwrap();
+		recorder.write_all(b"\t\\ \x07").unwrap();
+		let out = recorder.finish().unwrap();
+
+		let cast = Asciicast::read(&out[..]).unwrap();
+		assert_eq!(cast.header, header);
+		let events: Vec<(f64, char, &str)> = cast.events.iter().map(|e| (e.time, e.kind, e.data.as_str())).collect();
+		assert_eq!(
+			events,
+			vec![
+				(0.0, 'o', "\x1b[2J\x1b[1;1Hhello\r\n"),
+				(0.5, 'm', "frame 2"),
+				(0.75, 'o', "\u{2603}\t\\ \x07"),
+			]
+		);
+
+		let mut replayed = vec![];
+		cast.play(&mut replayed, 0.0).unwrap();
+		assert_eq!(String::from_utf8(replayed).unwrap(), cast.output());
+		assert_eq!(cast.output(), "\x1b[2J\x1b[1;1Hhello\r\n\u{2603}\t\\ \x07");
+	}
+
+	#[test]
+	fn read_errors() {
+		assert!(Asciicast::read(&b""[..]).is_err());
+		assert!(Asciicast::read(&b"{\"version\": 1, \"width\": 80, \"height\": 24}"[..]).is_err());
+		assert!(Asciicast::read(&b"{\"version\": 2, \"width\": 80, \"height\": 24}\n[0.1, \"o\"]"[..]).is_err());
+	}
+
+	#[test]
+	fn parse_json() {
+		match json::parse(r#" {"a": [1, -2.5e1, true, null], "b": "x\"é\n"} "#).unwrap() {
+			json::Value::Object(object) => {
+				let numbers: Vec<f64> = match &object["a"] {
+					json::Value::Array(a) => a
+						.iter()
+						.filter_map(|v| if let json::Value::Number(n) = v { Some(*n) } else { None })
+						.collect(),
+					v => panic!("{:?}", v),
+				};
+				assert_eq!(numbers, vec![1.0, -25.0]);
+				assert!(matches!(&object["b"], json::Value::String(s) if s == "x\"\u{e9}\n"));
+			}
+			v => panic!("{:?}", v),
+		}
+		assert!(json::parse("[1, 2").is_err());
+		assert!(json::parse("{\"a\" 1}").is_err());
+		assert!(json::parse("1 2").is_err());
+	}
+}
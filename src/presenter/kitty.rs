@@ -0,0 +1,185 @@
+use crate::{Buffer, Color};
+use std::io::{self, Write};
+
+// Largest base64 payload allowed in a single escape sequence
+const CHUNK_SIZE: usize = 4096;
+const BASE64_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Query sent to check for Kitty graphics support, answered with `ESC _ G i=31;OK ESC \`
+pub const QUERY_KITTY_GRAPHICS: &str = "\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\";
+
+/// How image data is sent to the terminal
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum KittyFormat {
+	/// Uncompressed 32-bit RGBA, cheap to encode but large
+	#[default]
+	Rgba,
+	/// PNG compressed, smaller on slow connections
+	Png,
+}
+
+/// Encodes buffers using the Kitty terminal graphics protocol.
+///
+/// Every frame is sent with the same image and placement id, so the terminal
+/// replaces the previous frame instead of stacking new images on top.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KittyEncoder {
+	pub format: KittyFormat,
+	image_id: u32,
+}
+
+impl Default for KittyEncoder {
+	fn default() -> Self {
+		Self::new(KittyFormat::default())
+	}
+}
+
+impl KittyEncoder {
+	pub fn new(format: KittyFormat) -> Self {
+		Self::with_image_id(format, 1)
+	}
+
+	pub fn with_image_id(format: KittyFormat, image_id: u32) -> Self {
+		Self {
+			format,
+			image_id: image_id.max(1),
+		}
+	}
+
+	pub fn image_id(&self) -> u32 {
+		self.image_id
+	}
+
+	/// Transmit `buffer` and display it at the cursor, without moving the cursor
+	pub fn encode(&self, buffer: &Buffer<Color>, out: &mut impl Write) -> io::Result<()> {
+		let (w, h) = buffer.size();
+		let (format, data) = match self.format {
+			KittyFormat::Rgba => (format!("f=32,s={},v={}", w, h), buffer.as_bytes().to_vec()),
+			KittyFormat::Png => {
+				let mut png = vec![];
+				image::png::PngEncoder::new(&mut png)
+					.encode(buffer.as_bytes(), w, h, image::ColorType::Rgba8)
+					.map_err(io::Error::other)?;
+				(String::from("f=100"), png)
+			}
+		};
+
+		let payload = base64_encode(&data);
+		let mut chunks = payload.as_bytes().chunks(CHUNK_SIZE).peekable();
+		let mut first = true;
+		while let Some(chunk) = chunks.next() {
+			let more = if chunks.peek().is_some() { 1 } else { 0 };
+			if first {
+				write!(out, "\x1b_Ga=T,{},i={},p=1,q=2,C=1,m={};", format, self.image_id, more)?;
+				first = false;
+			} else {
+				write!(out, "\x1b_Gm={};", more)?;
+			}
+			out.write_all(chunk)?;
+			out.write_all(b"\x1b\\")?;
+		}
+		Ok(())
+	}
+
+	/// Remove the image from the terminal and free its data
+	pub fn delete(&self, out: &mut impl Write) -> io::Result<()> {
+		write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", self.image_id)
+	}
+}
+
+fn base64_encode(data: &[u8]) -> String {
+	let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+	for chunk in data.chunks(3) {
+		let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+		let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+		for i in 0..4 {
+			if i <= chunk.len() {
+				encoded.push(BASE64_CHARS[(n >> (18 - i * 6) & 0x3f) as usize] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	encoded
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn base64_decode(data: &str) -> Vec<u8> {
+		let mut decoded = vec![];
+		for chunk in data.as_bytes().chunks(4) {
+			let mut n = 0;
+			let mut len = 0;
+			for (i, c) in chunk.iter().enumerate() {
+				if let Some(v) = BASE64_CHARS.iter().position(|b| b == c) {
+					n |= (v as u32) << (18 - i * 6);
+					len = i;
+				}
+			}
+			decoded.extend_from_slice(&n.to_be_bytes()[1..=len]);
+		}
+		decoded
+	}
+
+	// Join the chunks of an encoded image, returning the first chunk's keys and the payload
+	fn decode(data: &str) -> (String, Vec<u8>) {
+		let mut keys = None;
+		let mut payload = String::new();
+		let mut sequences = data.split("\x1b\\").filter(|s| !s.is_empty()).peekable();
+		while let Some(sequence) = sequences.next() {
+			let (control, chunk) = sequence.strip_prefix("\x1b_G").unwrap().split_once(';').unwrap();
+			let more = if sequences.peek().is_some() { "m=1" } else { "m=0" };
+			assert!(control.ends_with(more));
+			keys.get_or_insert_with(|| control.to_string());
+			payload.push_str(chunk);
+		}
+		(keys.unwrap(), base64_decode(&payload))
+	}
+
+	fn gradient(w: u32, h: u32) -> Buffer<Color> {
+		let mut buffer = Buffer::new(w, h);
+		for y in 0..h as i32 {
+			for x in 0..w as i32 {
+				*buffer.get_mut(x, y).unwrap() = Color::rgba(x as u8 * 6, y as u8 * 8, 128, 255 - x as u8);
+			}
+		}
+		buffer
+	}
+
+	#[test]
+	fn base64() {
+		assert_eq!(base64_encode(b"Man"), "TWFu");
+		assert_eq!(base64_encode(b"Ma"), "TWE=");
+		assert_eq!(base64_encode(b"M"), "TQ==");
+		assert_eq!(base64_decode("TWE="), b"Ma");
+	}
+
+	#[test]
+	fn rgba_round_trip() {
+		// Large enough to need several chunks
+		let buffer = gradient(40, 30);
+		let mut out = vec![];
+		KittyEncoder::with_image_id(KittyFormat::Rgba, 7).encode(&buffer, &mut out).unwrap();
+		let out = String::from_utf8(out).unwrap();
+		assert!(out.matches("\x1b_G").count() > 1);
+
+		let (keys, data) = decode(&out);
+		assert!(keys.starts_with("a=T,f=32,s=40,v=30,i=7,"));
+		assert_eq!(data, buffer.as_bytes());
+	}
+
+	#[test]
+	fn png_round_trip() {
+		let buffer = gradient(40, 30);
+		let mut out = vec![];
+		KittyEncoder::new(KittyFormat::Png).encode(&buffer, &mut out).unwrap();
+
+		let (keys, data) = decode(&String::from_utf8(out).unwrap());
+		assert!(keys.starts_with("a=T,f=100,"));
+		let image = image::load_from_memory(&data).unwrap().to_rgba();
+		assert_eq!((image.width(), image.height()), (40, 30));
+		assert_eq!(image.into_raw(), buffer.as_bytes());
+	}
+}
@@ -0,0 +1,217 @@
+use crate::{Buffer, Color, Palette};
+use std::io::{self, Write};
+
+pub const DEFAULT_SIXEL_COLORS: usize = 256;
+
+/// Encodes buffers as DEC Sixel graphics
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SixelEncoder {
+	/// Size of the palette generated for each image, most terminals support up to 256
+	pub max_colors: usize,
+}
+
+impl Default for SixelEncoder {
+	fn default() -> Self {
+		Self::new(DEFAULT_SIXEL_COLORS)
+	}
+}
+
+impl SixelEncoder {
+	pub fn new(max_colors: usize) -> Self {
+		Self { max_colors }
+	}
+
+	/// Write `buffer` as a Sixel image at the cursor position. Pixels with less
+	/// than 50% alpha are drawn in the terminal's background colour.
+	pub fn encode(&self, buffer: &Buffer<Color>, out: &mut impl Write) -> io::Result<()> {
+		let (w, h) = (buffer.width() as usize, buffer.height() as usize);
+		let mut palette = Palette::median_cut(buffer, self.max_colors.clamp(1, 256));
+
+		// Fill with the background colour first so the previous frame is erased, with a 1:1 pixel
+		// aspect ratio
+		write!(out, "\x1bP0;0;0q\"1;1;{};{}", w, h)?;
+		for (i, c) in palette.colors().iter().enumerate() {
+			write!(
+				out,
+				"#{};2;{};{};{}",
+				i,
+				c.r as u32 * 100 / 255,
+				c.g as u32 * 100 / 255,
+				c.b as u32 * 100 / 255
+			)?;
+		}
+
+		let indices: Vec<Option<usize>> = buffer
+			.as_slice()
+			.iter()
+			.map(|p| if p.a >= 128 { Some(palette.nearest(p)) } else { None })
+			.collect();
+
+		// Sixel bits for each colour used in the current band, one byte per column
+		let mut bands: Vec<Option<Vec<u8>>> = vec![None; palette.len()];
+		for top in (0..h).step_by(6) {
+			for dy in 0..6.min(h - top) {
+				let row = &indices[(top + dy) * w..(top + dy + 1) * w];
+				for (x, idx) in row.iter().enumerate() {
+					if let Some(idx) = idx {
+						bands[*idx].get_or_insert_with(|| vec![0; w])[x] |= 1 << dy;
+					}
+				}
+			}
+
+			let mut first = true;
+			for (i, band) in bands.iter_mut().enumerate() {
+				if let Some(bits) = band.take() {
+					if !first {
+						out.write_all(b"$")?;
+					}
+					first = false;
+					write!(out, "#{}", i)?;
+					write_sixels(out, &bits)?;
+				}
+			}
+			out.write_all(b"-")?;
+		}
+
+		out.write_all(b"\x1b\\")
+	}
+}
+
+// Write a row of sixels, run length encoded
+fn write_sixels(out: &mut impl Write, bits: &[u8]) -> io::Result<()> {
+	// Nothing needs drawing after the last set bit
+	let len = bits.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+	let mut x = 0;
+	while x < len {
+		let value = bits[x];
+		let mut run = 1;
+		while x + run < len && bits[x + run] == value {
+			run += 1;
+		}
+		let ch = 63 + value;
+		if run > 3 {
+			write!(out, "!{}{}", run, ch as char)?;
+		} else {
+			for _ in 0..run {
+				out.write_all(&[ch])?;
+			}
+		}
+		x += run;
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Decode Sixel data back to pixels, `None` where nothing was drawn
+	fn decode(data: &str) -> (usize, usize, Vec<Option<Color>>) {
+		let body = data
+			.strip_prefix("\x1bP0;0;0q\"1;1;")
+			.unwrap()
+			.strip_suffix("\x1b\\")
+			.unwrap();
+		let mut chars = body.chars().peekable();
+		let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+			let mut n = 0;
+			while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+				n = n * 10 + d as usize;
+				chars.next();
+			}
+			n
+		};
+		let w = number(&mut chars);
+		assert_eq!(chars.next(), Some(';'));
+		let h = number(&mut chars);
+
+		let mut palette = vec![];
+		let mut pixels = vec![None; w * h];
+		let (mut x, mut top, mut color) = (0, 0, 0);
+		while let Some(c) = chars.next() {
+			match c {
+				'#' => {
+					color = number(&mut chars);
+					if chars.peek() == Some(&';') {
+						let mut rgb = [0; 4];
+						for v in rgb.iter_mut() {
+							chars.next();
+							*v = number(&mut chars);
+						}
+						let level = |v: usize| (v * 255 / 100) as u8;
+						palette.resize(palette.len().max(color + 1), Color::default());
+						palette[color] = Color::rgb(level(rgb[1]), level(rgb[2]), level(rgb[3]));
+					}
+				}
+				'$' => x = 0,
+				'-' => {
+					x = 0;
+					top += 6;
+				}
+				'!' | '?'..='~' => {
+					let (run, c) = if c == '!' {
+						let run = number(&mut chars);
+						(run, chars.next().unwrap())
+					} else {
+						(1, c)
+					};
+					let bits = c as u8 - 63;
+					for _ in 0..run {
+						for dy in 0..6 {
+							if bits & (1 << dy) != 0 {
+								pixels[(top + dy) * w + x] = Some(palette[color]);
+							}
+						}
+						x += 1;
+					}
+				}
+				_ => panic!("unexpected {:?}", c),
+			}
+		}
+		(w, h, pixels)
+	}
+
+	#[test]
+	fn round_trip() {
+		let (w, h) = (13, 9);
+		let mut buffer = Buffer::new(w, h);
+		for y in 0..h as i32 {
+			for x in 0..w as i32 {
+				let color = match (x + y) % 3 {
+					0 => Color::rgb(255, 0, 0),
+					1 => Color::rgb(0, 255, 0),
+					_ => Color::rgba(0, 0, 255, 0),
+				};
+				*buffer.get_mut(x, y).unwrap() = color;
+			}
+		}
+
+		let mut out = vec![];
+		SixelEncoder::default().encode(&buffer, &mut out).unwrap();
+		let (dw, dh, pixels) = decode(std::str::from_utf8(&out).unwrap());
+		assert_eq!((dw, dh), (w as usize, h as usize));
+		for (pixel, decoded) in buffer.as_slice().iter().zip(pixels) {
+			let expected = if pixel.a >= 128 {
+				Some(Color::rgb(pixel.r, pixel.g, pixel.b))
+			} else {
+				None
+			};
+			assert_eq!(decoded, expected);
+		}
+	}
+
+	#[test]
+	fn transparent_only() {
+		// Larger than the palette's sample limit, with a single opaque pixel
+		let mut buffer = Buffer::new(512, 256);
+		*buffer.get_mut(1, 1).unwrap() = Color::rgb(255, 255, 255);
+		let mut out = vec![];
+		SixelEncoder::default().encode(&buffer, &mut out).unwrap();
+		let (_, _, pixels) = decode(std::str::from_utf8(&out).unwrap());
+		assert_eq!(pixels.iter().flatten().count(), 1);
+
+		let mut out = vec![];
+		SixelEncoder::default().encode(&Buffer::new(4, 4), &mut out).unwrap();
+		assert!(decode(std::str::from_utf8(&out).unwrap()).2.iter().all(Option::is_none));
+	}
+}
@@ -1,24 +1,19 @@
-use crate::buffer::{Blendable, Buffer};
-use image::io::Reader as ImageReader;
-use std::error::Error;
-
-#[derive(Copy, Clone, Debug)]
-pub enum TextureWrap {
-	Clamp,
-	Repeat,
-}
+mod data;
+pub use data::*;
+mod sampler;
+pub use sampler::*;
 
-#[derive(Copy, Clone, Debug)]
-pub enum TextureFilter {
-	Nearest,
-	Bilinear,
-}
+use crate::buffer::{Blendable, Buffer, Filterable};
+use nalgebra as na;
+use std::error::Error;
+use std::sync::Arc;
 
+/// Shared texture data paired with a `Sampler`. Cloning only copies the sampler, so the same image
+/// can be used with different filtering or wrapping cheaply.
 #[derive(Clone)]
 pub struct Texture<P: Blendable> {
-	buffer: Buffer<P>,
-	wrap: TextureWrap,
-	filter: TextureFilter,
+	data: Arc<TextureData<P>>,
+	sampler: Sampler<P>,
 	emissive: bool,
 }
 
@@ -27,26 +22,7 @@ where
 	P: Blendable + From<[u8; 4]>,
 {
 	pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
-		log::debug!("Loading image: {}", path);
-		let img = ImageReader::open(path)?.decode()?.to_rgba();
-		let width = img.width();
-		let height = img.height();
-		log::debug!("Loaded image: {} - {}x{}", path, width, height);
-
-		let mut buffer: Buffer<P> = Buffer::new(width, height);
-		for y in 0..height {
-			for x in 0..width {
-				if let Some(p) = buffer.get_mut(x as i32, y as i32) {
-					*p = img.get_pixel(x, y).0.into();
-				}
-			}
-		}
-		Ok(Self {
-			buffer,
-			wrap: TextureWrap::Clamp,
-			filter: TextureFilter::Bilinear,
-			emissive: false,
-		})
+		Ok(Self::from_data(Arc::new(TextureData::load(path)?), Sampler::default()))
 	}
 }
 
@@ -55,28 +31,89 @@ where
 	P: Blendable,
 {
 	pub fn new(width: u32, height: u32) -> Self {
+		Self::from_data(Arc::new(TextureData::new(width, height)), Sampler::default())
+	}
+
+	pub fn from_data(data: Arc<TextureData<P>>, sampler: Sampler<P>) -> Self {
 		Self {
-			buffer: Buffer::new(width, height),
-			wrap: TextureWrap::Clamp,
-			filter: TextureFilter::Bilinear,
+			data,
+			sampler,
 			emissive: false,
 		}
 	}
 
+	pub fn data(&self) -> &Arc<TextureData<P>> {
+		&self.data
+	}
+
+	/// Image data for changing, copied first if it's shared with other textures
+	pub fn data_mut(&mut self) -> &mut TextureData<P> {
+		Arc::make_mut(&mut self.data)
+	}
+
+	pub fn sampler(&self) -> &Sampler<P> {
+		&self.sampler
+	}
+
+	pub fn sampler_mut(&mut self) -> &mut Sampler<P> {
+		&mut self.sampler
+	}
+
+	/// Same image, sampled with `sampler`
+	pub fn with_sampler(mut self, sampler: Sampler<P>) -> Self {
+		self.sampler = sampler;
+		self
+	}
+
+	/// Wrap mode for the U (x) axis
+	pub fn wrap_u(&self) -> TextureWrap {
+		self.sampler.wrap_u
+	}
+
+	pub fn wrap_u_mut(&mut self) -> &mut TextureWrap {
+		&mut self.sampler.wrap_u
+	}
+
+	/// Wrap mode for the V (y) axis
+	pub fn wrap_v(&self) -> TextureWrap {
+		self.sampler.wrap_v
+	}
+
+	pub fn wrap_v_mut(&mut self) -> &mut TextureWrap {
+		&mut self.sampler.wrap_v
+	}
+
+	#[deprecated(note = "textures wrap each axis separately, use `wrap_u` and `wrap_v`")]
 	pub fn wrap(&self) -> TextureWrap {
-		self.wrap
+		self.sampler.wrap_u
 	}
 
+	#[deprecated(note = "only changes the U axis, use `set_wrap` for both or `wrap_u_mut` and `wrap_v_mut`")]
 	pub fn wrap_mut(&mut self) -> &mut TextureWrap {
-		&mut self.wrap
+		&mut self.sampler.wrap_u
+	}
+
+	/// Set the wrap mode of both axes
+	pub fn set_wrap(&mut self, wrap: TextureWrap) {
+		self.sampler.wrap_u = wrap;
+		self.sampler.wrap_v = wrap;
+	}
+
+	/// Sampled outside the texture when wrapping with `TextureWrap::ClampToBorder`
+	pub fn border_color(&self) -> P {
+		self.sampler.border_color
+	}
+
+	pub fn set_border_color(&mut self, border_color: P) {
+		self.sampler.border_color = border_color;
 	}
 
 	pub fn filter(&self) -> TextureFilter {
-		self.filter
+		self.sampler.filter
 	}
 
 	pub fn filter_mut(&mut self) -> &mut TextureFilter {
-		&mut self.filter
+		&mut self.sampler.filter
 	}
 
 	pub fn emissive(&self) -> bool {
@@ -92,63 +129,66 @@ where
 	}
 
 	pub fn width(&self) -> u32 {
-		self.buffer.width()
+		self.data.width()
 	}
 
 	pub fn height(&self) -> u32 {
-		self.buffer.height()
+		self.data.height()
+	}
+
+	/// Number of mip levels, including the full size texture
+	pub fn mip_levels(&self) -> usize {
+		self.data.mip_levels()
+	}
+
+	/// Level 0 is the full size texture
+	pub fn mip_level(&self, level: usize) -> Option<&Buffer<P>> {
+		self.data.mip_level(level)
+	}
+
+	/// Mip level of detail for a change in UV of `ddx` and `ddy` from one screen pixel to the next
+	/// horizontally and vertically
+	pub fn lod(&self, ddx: na::Vector2<f32>, ddy: na::Vector2<f32>) -> f32 {
+		self.data.lod(ddx, ddy)
 	}
 
 	pub fn get_normalized_pixel(&self, x: f32, y: f32) -> P {
-		let xf = x * (self.width() - 1) as f32;
-		let yf = y * (self.height() - 1) as f32;
-
-		let xi = xf.floor() as i32;
-		let yi = yf.floor() as i32;
-		match self.filter {
-			TextureFilter::Nearest => self.get_pixel(xi, yi).clone(),
-			TextureFilter::Bilinear => {
-				let tl = self.get_pixel(xi, yi);
-				let tr = self.get_pixel(xi + 1, yi);
-				let bl = self.get_pixel(xi, yi + 1);
-				let br = self.get_pixel(xi + 1, yi + 1);
-
-				let xn = xf.fract();
-				let yn = yf.fract();
-				let t = tl.lerp(&tr, xn);
-				let b = bl.lerp(&br, xn);
-
-				t.lerp(&b, yn)
-			}
-		}
+		self.sampler.sample(&self.data, x, y)
 	}
 
-	pub fn get_pixel(&self, mut x: i32, mut y: i32) -> &P {
-		match self.wrap {
-			TextureWrap::Clamp => {
-				if x >= self.width() as i32 {
-					x = self.width() as i32 - 1;
-				}
-				if y >= self.height() as i32 {
-					y = self.height() as i32 - 1;
-				}
-				if x < 0 {
-					x = 0;
-				}
-				if y < 0 {
-					y = 0;
-				}
-			}
-			TextureWrap::Repeat => {
-				x = x.rem_euclid(self.width() as i32 - 1);
-				y = y.rem_euclid(self.height() as i32 - 1);
-			}
-		}
+	/// Sample at `x`, `y` from mip level `lod`, see `Sampler::sample_lod`
+	pub fn sample_lod(&self, x: f32, y: f32, lod: f32) -> P {
+		self.sampler.sample_lod(&self.data, x, y, lod)
+	}
 
-		self.buffer.get(x, y).unwrap()
+	/// Texel at `x`, `y`, wrapped when it's outside the texture
+	pub fn get_pixel(&self, x: i32, y: i32) -> &P {
+		self.sampler.texel(self.data.buffer(), x, y)
 	}
 
+	/// When the image is shared with other textures, the first write copies all of it so the others
+	/// aren't changed. For many writes, `data_mut` once and edit its buffer directly.
 	pub fn get_pixel_mut(&mut self, x: i32, y: i32) -> &mut P {
-		self.buffer.get_mut(x, y).unwrap()
+		self.data_mut().buffer_mut().get_mut(x, y).unwrap()
+	}
+}
+
+impl<P> Texture<P>
+where
+	P: Filterable,
+{
+	/// Build the chain of mip levels down to 1x1 pixel. Needs calling again after the texture changes.
+	pub fn generate_mipmaps(&mut self, filter: MipmapFilter) {
+		self.data_mut().generate_mipmaps(filter);
+	}
+
+	pub fn with_mipmaps(mut self, filter: MipmapFilter) -> Self {
+		self.generate_mipmaps(filter);
+		self
+	}
+
+	/// Sample at `x`, `y` using the UV derivatives `ddx` and `ddy` to pick the mip level
+	pub fn sample_grad(&self, x: f32, y: f32, ddx: na::Vector2<f32>, ddy: na::Vector2<f32>) -> P {
+		self.sampler.sample_grad(&self.data, x, y, ddx, ddy)
 	}
 }
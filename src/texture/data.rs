@@ -0,0 +1,212 @@
+use crate::buffer::{Blendable, Buffer, Filterable};
+use image::io::Reader as ImageReader;
+use nalgebra as na;
+use std::error::Error;
+
+/// How each mip level is averaged down from the one above it
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum MipmapFilter {
+	/// Average the stored values
+	Box,
+	/// Average in linear light, so sRGB textures don't get darker as they shrink
+	#[default]
+	GammaCorrect,
+}
+
+/// Texture image and its mip levels, without any sampling state. Wrap it in an `Arc` to share it
+/// between textures and shaders, and sample it with a `Sampler`.
+#[derive(Clone, Debug, Default)]
+pub struct TextureData<P: Blendable> {
+	buffer: Buffer<P>,
+	// Each half the size of the previous, starting at half the size of `buffer`
+	mipmaps: Vec<Buffer<P>>,
+}
+
+impl<P> TextureData<P>
+where
+	P: Blendable + From<[u8; 4]>,
+{
+	pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+		log::debug!("Loading image: {}", path);
+		let img = ImageReader::open(path)?.decode()?.to_rgba();
+		let width = img.width();
+		let height = img.height();
+		log::debug!("Loaded image: {} - {}x{}", path, width, height);
+
+		let mut buffer: Buffer<P> = Buffer::new(width, height);
+		for y in 0..height {
+			for x in 0..width {
+				if let Some(p) = buffer.get_mut(x as i32, y as i32) {
+					*p = img.get_pixel(x, y).0.into();
+				}
+			}
+		}
+		Ok(buffer.into())
+	}
+}
+
+impl<P: Blendable> From<Buffer<P>> for TextureData<P> {
+	fn from(buffer: Buffer<P>) -> Self {
+		Self {
+			buffer,
+			mipmaps: vec![],
+		}
+	}
+}
+
+impl<P> TextureData<P>
+where
+	P: Blendable,
+{
+	pub fn new(width: u32, height: u32) -> Self {
+		Buffer::new(width, height).into()
+	}
+
+	pub fn width(&self) -> u32 {
+		self.buffer.width()
+	}
+
+	pub fn height(&self) -> u32 {
+		self.buffer.height()
+	}
+
+	/// The full size image
+	pub fn buffer(&self) -> &Buffer<P> {
+		&self.buffer
+	}
+
+	/// Mip levels aren't updated when the image is changed, so regenerate them afterwards
+	pub fn buffer_mut(&mut self) -> &mut Buffer<P> {
+		&mut self.buffer
+	}
+
+	/// Number of mip levels, including the full size image
+	pub fn mip_levels(&self) -> usize {
+		self.mipmaps.len() + 1
+	}
+
+	/// Level 0 is the full size image
+	pub fn mip_level(&self, level: usize) -> Option<&Buffer<P>> {
+		match level {
+			0 => Some(&self.buffer),
+			_ => self.mipmaps.get(level - 1),
+		}
+	}
+
+	/// Mip level of detail for a change in UV of `ddx` and `ddy` from one screen pixel to the next
+	/// horizontally and vertically
+	pub fn lod(&self, ddx: na::Vector2<f32>, ddy: na::Vector2<f32>) -> f32 {
+		let size = na::Vector2::new(self.width() as f32, self.height() as f32);
+		let dx = ddx.component_mul(&size).norm_squared();
+		let dy = ddy.component_mul(&size).norm_squared();
+		// log2 of the longest side of the pixel's footprint, in texels
+		(dx.max(dy).log2() / 2.0).max(0.0)
+	}
+}
+
+impl<P> TextureData<P>
+where
+	P: Filterable,
+{
+	/// Build the chain of mip levels down to 1x1 pixel. Needs calling again after the image changes.
+	pub fn generate_mipmaps(&mut self, filter: MipmapFilter) {
+		self.mipmaps.clear();
+		let mut level = &self.buffer;
+		while level.width() > 1 || level.height() > 1 {
+			let next = downsample(level, filter);
+			self.mipmaps.push(next);
+			level = self.mipmaps.last().unwrap();
+		}
+	}
+
+	pub fn with_mipmaps(mut self, filter: MipmapFilter) -> Self {
+		self.generate_mipmaps(filter);
+		self
+	}
+}
+
+// Half the size, rounded down. Each pixel averages the source pixels it covers, which is a 2x2 block
+// for even sizes. Halving an odd size covers 3 source pixels along that axis, partly weighting those
+// on either side, so no row or column is dropped.
+fn downsample<P: Filterable>(src: &Buffer<P>, filter: MipmapFilter) -> Buffer<P> {
+	let (width, height) = ((src.width() / 2).max(1), (src.height() / 2).max(1));
+	let columns = footprints(src.width(), width);
+	let rows = footprints(src.height(), height);
+	let mut dst = Buffer::new(width, height);
+	for (y, row) in rows.iter().enumerate() {
+		for (x, column) in columns.iter().enumerate() {
+			let mut sum = [0.0; 4];
+			for (sy, wy) in row {
+				for (sx, wx) in column {
+					let pixel = match (src.get(*sx, *sy), filter) {
+						(Some(p), MipmapFilter::Box) => p.to_floats(),
+						(Some(p), MipmapFilter::GammaCorrect) => p.to_linear_floats(),
+						(None, _) => continue,
+					};
+					for c in 0..4 {
+						sum[c] += pixel[c] * wx * wy;
+					}
+				}
+			}
+			if let Some(p) = dst.get_mut(x as i32, y as i32) {
+				*p = match filter {
+					MipmapFilter::Box => P::from_floats(sum),
+					MipmapFilter::GammaCorrect => P::from_linear_floats(sum),
+				};
+			}
+		}
+	}
+	dst
+}
+
+// Source pixels along one axis covered by each destination pixel, with how much of each is covered
+fn footprints(src: u32, dst: u32) -> Vec<Vec<(i32, f32)>> {
+	let scale = src as f32 / dst as f32;
+	(0..dst)
+		.map(|i| {
+			let (start, end) = (i as f32 * scale, (i + 1) as f32 * scale);
+			(start.floor() as u32..(end.ceil() as u32).min(src))
+				.map(|j| (j as i32, (end.min(j as f32 + 1.0) - start.max(j as f32)) / scale))
+				.filter(|(_, weight)| *weight > 0.0)
+				.collect()
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn row(values: &[f32]) -> Buffer<f32> {
+		let mut buffer = Buffer::new(values.len() as u32, 1);
+		for (x, v) in values.iter().enumerate() {
+			*buffer.get_mut(x as i32, 0).unwrap() = *v;
+		}
+		buffer
+	}
+
+	#[test]
+	fn even() {
+		let mip = downsample(&row(&[1.0, 3.0, 5.0, 7.0]), MipmapFilter::Box);
+		assert_eq!(mip.as_slice(), &[2.0, 6.0]);
+	}
+
+	#[test]
+	fn odd_edge_is_kept() {
+		// The last column used to be dropped, losing the bright pixel entirely
+		let mip = downsample(&row(&[0.0, 0.0, 0.0, 0.0, 10.0]), MipmapFilter::Box);
+		assert_eq!(mip.width(), 2);
+		assert!((mip.as_slice()[0]).abs() < 1e-6);
+		assert!((mip.as_slice()[1] - 4.0).abs() < 1e-5);
+
+		let mip = downsample(&row(&[3.0, 6.0, 9.0]), MipmapFilter::Box);
+		assert!((mip.as_slice()[0] - 6.0).abs() < 1e-5);
+	}
+
+	#[test]
+	fn chain() {
+		let data = TextureData::<f32>::new(5, 3).with_mipmaps(MipmapFilter::Box);
+		let sizes: Vec<(u32, u32)> = (0..data.mip_levels()).map(|l| data.mip_level(l).unwrap().size()).collect();
+		assert_eq!(sizes, vec![(5, 3), (2, 1), (1, 1)]);
+	}
+}
@@ -0,0 +1,293 @@
+use super::TextureData;
+use crate::buffer::{Blendable, Buffer, Filterable};
+use nalgebra as na;
+
+/// What is sampled past the edges of a texture, set separately for U and V
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum TextureWrap {
+	/// Repeat the edge texels
+	#[default]
+	ClampToEdge,
+	/// Use the sampler's border colour
+	ClampToBorder,
+	/// Tile the texture
+	Repeat,
+	/// Tile the texture, flipping every other copy so the edges meet seamlessly
+	MirroredRepeat,
+}
+
+impl TextureWrap {
+	#[deprecated(note = "renamed to `TextureWrap::ClampToEdge`")]
+	#[allow(non_upper_case_globals)]
+	pub const Clamp: TextureWrap = TextureWrap::ClampToEdge;
+
+	// Texel `i` of a row or column `len` texels long, `None` for the border colour
+	fn apply(&self, i: i32, len: i32) -> Option<i32> {
+		if len <= 0 {
+			return None;
+		}
+		match self {
+			TextureWrap::ClampToEdge => Some(i.clamp(0, len - 1)),
+			TextureWrap::ClampToBorder if i < 0 || i >= len => None,
+			TextureWrap::ClampToBorder => Some(i),
+			TextureWrap::Repeat => Some(i.rem_euclid(len)),
+			TextureWrap::MirroredRepeat => {
+				let i = i.rem_euclid(len * 2);
+				Some(if i < len { i } else { len * 2 - 1 - i })
+			}
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextureFilter {
+	Nearest,
+	Bilinear,
+	/// Nearest texel of the closest mip level
+	NearestMipmap,
+	/// Bilinear filtering of the two closest mip levels, blended together
+	Trilinear,
+}
+
+/// How texture data is read: filtering, wrapping and mip level selection. Kept apart from the
+/// image so one image can be sampled in different ways without copying it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sampler<P: Blendable> {
+	pub filter: TextureFilter,
+	pub wrap_u: TextureWrap,
+	pub wrap_v: TextureWrap,
+	/// Sampled outside the texture when wrapping with `TextureWrap::ClampToBorder`
+	pub border_color: P,
+	/// Added to the level of detail before picking a mip level. Below zero is sharper, above is blurrier.
+	pub lod_bias: f32,
+	/// Most samples `sample_grad` takes along a stretched footprint, e.g. a floor seen at a shallow
+	/// angle. 1 disables anisotropic filtering.
+	pub max_anisotropy: u32,
+}
+
+impl<P: Blendable> Default for Sampler<P> {
+	fn default() -> Self {
+		Self {
+			filter: TextureFilter::Bilinear,
+			wrap_u: TextureWrap::ClampToEdge,
+			wrap_v: TextureWrap::ClampToEdge,
+			border_color: P::default(),
+			lod_bias: 0.0,
+			max_anisotropy: 1,
+		}
+	}
+}
+
+impl<P: Blendable> Sampler<P> {
+	pub fn new(filter: TextureFilter) -> Self {
+		Self {
+			filter,
+			..Self::default()
+		}
+	}
+
+	/// Use `wrap` for both axes
+	pub fn with_wrap(self, wrap: TextureWrap) -> Self {
+		self.with_wrap_uv(wrap, wrap)
+	}
+
+	pub fn with_wrap_uv(mut self, wrap_u: TextureWrap, wrap_v: TextureWrap) -> Self {
+		self.wrap_u = wrap_u;
+		self.wrap_v = wrap_v;
+		self
+	}
+
+	pub fn with_border_color(mut self, border_color: P) -> Self {
+		self.border_color = border_color;
+		self
+	}
+
+	pub fn with_lod_bias(mut self, lod_bias: f32) -> Self {
+		self.lod_bias = lod_bias;
+		self
+	}
+
+	pub fn with_anisotropy(mut self, max_anisotropy: u32) -> Self {
+		self.max_anisotropy = max_anisotropy.max(1);
+		self
+	}
+
+	/// Sample the full size image at `x`, `y`, normalized to `0.0..=1.0`
+	pub fn sample(&self, data: &TextureData<P>, x: f32, y: f32) -> P {
+		self.sample_lod(data, x, y, 0.0)
+	}
+
+	/// Sample at `x`, `y` from mip level `lod` plus the sampler's bias, where 0.0 is full size and
+	/// each level above is half the size. Fractional levels are blended by `TextureFilter::Trilinear`.
+	/// The `Nearest` and `Bilinear` filters always sample the full size image.
+	pub fn sample_lod(&self, data: &TextureData<P>, x: f32, y: f32, lod: f32) -> P {
+		let max_level = (data.mip_levels() - 1) as f32;
+		let lod = lod + self.lod_bias;
+		let lod = if lod.is_nan() { 0.0 } else { lod.clamp(0.0, max_level) };
+		match self.filter {
+			TextureFilter::Nearest => self.sample_level(data, 0, x, y, false),
+			TextureFilter::Bilinear => self.sample_level(data, 0, x, y, true),
+			TextureFilter::NearestMipmap => self.sample_level(data, lod.round() as usize, x, y, false),
+			TextureFilter::Trilinear => {
+				let level = lod.floor() as usize;
+				let fine = self.sample_level(data, level, x, y, true);
+				if lod.fract() == 0.0 {
+					fine
+				} else {
+					fine.lerp(&self.sample_level(data, level + 1, x, y, true), lod.fract())
+				}
+			}
+		}
+	}
+
+	// `x` and `y` are normalized, with texel centres at half texel offsets
+	fn sample_level(&self, data: &TextureData<P>, level: usize, x: f32, y: f32, bilinear: bool) -> P {
+		let buffer = data.mip_level(level).unwrap_or_else(|| data.buffer());
+		let xf = x * buffer.width() as f32;
+		let yf = y * buffer.height() as f32;
+		if !bilinear {
+			return *self.texel(buffer, xf.floor() as i32, yf.floor() as i32);
+		}
+
+		// Offset so the four nearest texel centres surround the sample
+		let xf = xf - 0.5;
+		let yf = yf - 0.5;
+		let xi = xf.floor() as i32;
+		let yi = yf.floor() as i32;
+		let tl = self.texel(buffer, xi, yi);
+		let tr = self.texel(buffer, xi + 1, yi);
+		let bl = self.texel(buffer, xi, yi + 1);
+		let br = self.texel(buffer, xi + 1, yi + 1);
+
+		let xn = xf - xi as f32;
+		let yn = yf - yi as f32;
+		let t = tl.lerp(tr, xn);
+		let b = bl.lerp(br, xn);
+
+		t.lerp(&b, yn)
+	}
+
+	/// Texel at `x`, `y` of `buffer`, wrapped when it's outside
+	pub fn texel<'a>(&'a self, buffer: &'a Buffer<P>, x: i32, y: i32) -> &'a P {
+		let x = self.wrap_u.apply(x, buffer.width() as i32);
+		let y = self.wrap_v.apply(y, buffer.height() as i32);
+		match (x, y) {
+			(Some(x), Some(y)) => buffer.get(x, y).unwrap_or(&self.border_color),
+			_ => &self.border_color,
+		}
+	}
+}
+
+impl<P: Filterable> Sampler<P> {
+	/// Sample at `x`, `y` using the UV derivatives `ddx` and `ddy` to pick the mip level. When
+	/// they're stretched more in one direction, up to `max_anisotropy` samples are averaged along it.
+	pub fn sample_grad(
+		&self,
+		data: &TextureData<P>,
+		x: f32,
+		y: f32,
+		ddx: na::Vector2<f32>,
+		ddy: na::Vector2<f32>,
+	) -> P {
+		if self.max_anisotropy <= 1 {
+			return self.sample_lod(data, x, y, data.lod(ddx, ddy));
+		}
+
+		let size = na::Vector2::new(data.width() as f32, data.height() as f32);
+		let (dx, dy) = (ddx.component_mul(&size).norm(), ddy.component_mul(&size).norm());
+		let (major, minor, axis) = if dx >= dy { (dx, dy, ddx) } else { (dy, dx, ddy) };
+		let count = if minor > 0.0 {
+			((major / minor).ceil() as u32).clamp(1, self.max_anisotropy)
+		} else {
+			self.max_anisotropy
+		};
+		// Each sample covers an equal part of the footprint, so needs less blurring, but never less
+		// than the footprint's width when there are too few samples to cover its length
+		let lod = (major / count as f32).max(minor).log2().max(0.0);
+
+		// Summed as floats so integer pixel types aren't truncated at every step
+		let mut sum = [0.0; 4];
+		for i in 0..count {
+			let offset = (i as f32 + 0.5) / count as f32 - 0.5;
+			let sample = self.sample_lod(data, x + axis.x * offset, y + axis.y * offset, lod).to_floats();
+			for c in 0..4 {
+				sum[c] += sample[c] / count as f32;
+			}
+		}
+		P::from_floats(sum)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::texture::MipmapFilter;
+	use crate::Color;
+
+	// Each mode at the texels either side of both edges
+	fn edges(wrap: TextureWrap, len: i32) -> [Option<i32>; 4] {
+		[wrap.apply(-1, len), wrap.apply(0, len), wrap.apply(len - 1, len), wrap.apply(len, len)]
+	}
+
+	#[test]
+	fn wrap_edges() {
+		assert_eq!(edges(TextureWrap::ClampToEdge, 4), [Some(0), Some(0), Some(3), Some(3)]);
+		assert_eq!(edges(TextureWrap::ClampToBorder, 4), [None, Some(0), Some(3), None]);
+		assert_eq!(edges(TextureWrap::Repeat, 4), [Some(3), Some(0), Some(3), Some(0)]);
+		assert_eq!(edges(TextureWrap::MirroredRepeat, 4), [Some(0), Some(0), Some(3), Some(3)]);
+	}
+
+	#[test]
+	fn wrap_far_outside() {
+		assert_eq!(TextureWrap::Repeat.apply(-9, 4), Some(3));
+		assert_eq!(TextureWrap::MirroredRepeat.apply(5, 4), Some(2));
+		assert_eq!(TextureWrap::MirroredRepeat.apply(-6, 4), Some(2));
+		assert_eq!(TextureWrap::ClampToEdge.apply(i32::MIN, 4), Some(0));
+	}
+
+	#[test]
+	fn wrap_single_texel() {
+		for wrap in [TextureWrap::ClampToEdge, TextureWrap::Repeat, TextureWrap::MirroredRepeat] {
+			assert_eq!(edges(wrap, 1), [Some(0); 4]);
+		}
+		assert_eq!(TextureWrap::Repeat.apply(0, 0), None);
+	}
+
+	fn texture<P: Filterable>(size: u32, pixel: impl Fn(i32, i32) -> P) -> TextureData<P> {
+		let mut buffer = Buffer::new(size, size);
+		for y in 0..size as i32 {
+			for x in 0..size as i32 {
+				*buffer.get_mut(x, y).unwrap() = pixel(x, y);
+			}
+		}
+		TextureData::from(buffer).with_mipmaps(MipmapFilter::Box)
+	}
+
+	#[test]
+	fn anisotropic_average() {
+		// Averaging used to truncate at every sample, darkening integer colours
+		let color = Color::rgba(201, 99, 7, 255);
+		let data = texture(16, |_, _| color);
+		let sampler = Sampler::new(TextureFilter::Trilinear).with_anisotropy(8);
+		let (ddx, ddy) = (na::Vector2::new(0.5, 0.0), na::Vector2::new(0.0, 1.0 / 16.0));
+		assert_eq!(sampler.sample_grad(&data, 0.5, 0.5, ddx, ddy), color);
+	}
+
+	#[test]
+	fn anisotropic_lod() {
+		// Stripes 2 texels tall, which average to 0.5 from mip level 2
+		let data = texture(16, |_, y| ((y / 2) % 2) as f32);
+		let sampler = Sampler::new(TextureFilter::Trilinear).with_anisotropy(8);
+		// 6 texels wide and 4 tall, so 2 samples whose footprints are still 4 texels tall
+		let (ddx, ddy) = (na::Vector2::new(6.0 / 16.0, 0.0), na::Vector2::new(0.0, 4.0 / 16.0));
+		let sample = sampler.sample_grad(&data, 0.5, 2.5 / 8.0, ddx, ddy);
+		assert!((sample - 0.5).abs() < 1e-6, "{}", sample);
+	}
+
+	#[test]
+	#[allow(deprecated)]
+	fn clamp_alias() {
+		assert_eq!(TextureWrap::Clamp, TextureWrap::ClampToEdge);
+		assert!(matches!(TextureWrap::ClampToEdge, TextureWrap::Clamp));
+	}
+}